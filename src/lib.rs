@@ -0,0 +1,60 @@
+//! Tiling wave function collapse.
+//!
+//! A [`Tileset`] is learned from a sample image by cutting it into
+//! [`TILE_SIZE`]×[`TILE_SIZE`] tiles and recording which tiles appear next to each
+//! other. A [`WaveFunction`] then fills an output grid with those tiles so that every
+//! pair of neighbours was also seen in the sample. Tilesets can also be assembled
+//! from individually drawn tiles and explicit rules with [`Tileset::from_manifest`],
+//! and stored with [`Tileset::save`] to skip the extraction on later runs. Long solves
+//! can be interrupted and resumed with [`WaveFunction::save_checkpoint`] when they are
+//! driven by a [`SolverRng`]. Grids other than the plain square one, like [`Hex`]
+//! maps, are plugged in as a [`Topology`], see [`WaveFunction::with_topology`].
+//! [`VoxelWaveFunction`] does the same in 3D over a [`VoxelTileset`] learned from a
+//! stack of png slices. Unbounded outputs are generated chunk by chunk with a
+//! [`World`], and finished ones grown on their sides with [`WaveFunction::extend`].
+//! A [`Batch`] solves one setup with many seeds and keeps the distinct outputs.
+//!
+//! ```no_run
+//! use tiling_wfc::{Tileset, WaveFunction};
+//!
+//! let tileset = Tileset::from_png("images/house.png");
+//! let mut rng = rand::thread_rng();
+//! loop {
+//!     let mut wave_function = WaveFunction::new((100, 100), tileset.clone());
+//!     if wave_function.run(&mut rng).is_ok() {
+//!         let tile_ids = wave_function.get_result().unwrap();
+//!         println!("{:?}", tile_ids);
+//!         break;
+//!     }
+//! }
+//! ```
+//!
+//! Rendering with raylib lives behind the `viewer` feature, which is enabled by
+//! default. Build with `--no-default-features` for a headless solver.
+
+pub mod wfc;
+
+pub use wfc::analysis::Analysis;
+pub use wfc::batch::{Batch, BatchOutput, DEFAULT_SEED_ATTEMPTS};
+pub use wfc::checkpoint::{CheckpointError, Checkpointing, Resumed};
+pub use wfc::constraints::TileCount;
+pub use wfc::extend::{load_tile_ids, Margins};
+pub use wfc::hex::{Hex, HexDirection, HexLayout};
+pub use wfc::limits::{Attempts, CancelToken, GaveUp, GiveUpReason, Limits, Progress};
+pub use wfc::observer::Observer;
+pub use wfc::parallel::{attempt_seed, Solved};
+pub use wfc::snapshot::Snapshot;
+pub use wfc::stats::SolveStats;
+pub use wfc::symmetry::{Mirror, Symmetry};
+pub use wfc::tileset::{
+    AtlasOptions, AtlasRect, Direction, Tile, Tileset, TilesetError, WfcColor, TILE_SIZE,
+};
+pub use wfc::topology::{Diagonals, Periodic, Square, Topology};
+#[cfg(feature = "viewer")]
+pub use wfc::viewer::Canvas;
+pub use wfc::voxel::{
+    tileset::{VoxelDirection, VoxelTile, VoxelTileset},
+    Layers, VoxelCoordinates, VoxelWaveFunction,
+};
+pub use wfc::world::{ChunkCoordinates, World, DEFAULT_CHUNK_ATTEMPTS};
+pub use wfc::{Contradiction, Coordinates, SolverRng, WaveFunction};
@@ -1,9 +1,27 @@
-use rand::{rngs::ThreadRng, thread_rng};
+use ndarray::Array2;
+use rand::{thread_rng, Rng, SeedableRng};
+#[cfg(feature = "viewer")]
 use raylib::prelude::*;
-use std::{collections::HashMap, env};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    env,
+    fmt::{self, Display},
+    fs,
+    path::Path,
+    process,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-mod wfc;
-use wfc::WaveFunction;
+use tiling_wfc::{
+    load_tile_ids, Analysis, AtlasOptions, Attempts, Batch, BatchOutput, Checkpointing,
+    Coordinates, GaveUp, Hex, Limits, Margins, Observer, Progress, Resumed, Snapshot, SolveStats,
+    SolverRng, Symmetry, TileCount, Tileset, TilesetError, VoxelTileset, VoxelWaveFunction,
+    WaveFunction, WfcColor, World, DEFAULT_CHUNK_ATTEMPTS, DEFAULT_SEED_ATTEMPTS, TILE_SIZE,
+};
+#[cfg(feature = "viewer")]
+use tiling_wfc::{Canvas, Contradiction, GiveUpReason};
 
 type Config = HashMap<String, bool>;
 
@@ -25,71 +43,1032 @@ fn get_config() -> Config {
     config
 }
 
+fn get_option(short: &str, long: &str) -> Option<String> {
+    let args = env::args().collect::<Vec<String>>();
+    let index = args.iter().position(|s| s.eq(short) || s.eq(long))?;
+    match args.get(index + 1) {
+        Some(value) => Some(value.clone()),
+        None => exit_with(format!("missing value for {long}")),
+    }
+}
+
+fn get_options(short: &str, long: &str) -> Vec<String> {
+    let args = env::args().collect::<Vec<String>>();
+    let mut values = Vec::new();
+    for (index, _) in args
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.eq(&short) || s.eq(&long))
+    {
+        match args.get(index + 1) {
+            Some(value) => values.push(value.clone()),
+            None => exit_with(format!("missing value for {long}")),
+        }
+    }
+    values
+}
+
+fn exit_with(err: impl Display) -> ! {
+    eprintln!("error: {err}");
+    process::exit(1)
+}
+
 const OUTPUT_SHAPE: (usize, usize) = (100, 100);
+const VOXEL_SHAPE: (usize, usize, usize) = (16, 16, 4);
+const HEX_SHAPE: (usize, usize) = (20, 20);
+const HEX_WIDTH: u32 = 24;
+const WORLD_CHUNKS: (usize, usize) = (4, 4);
+const CHUNK_SIZE: (usize, usize) = (16, 16);
+#[cfg(feature = "viewer")]
 const SHOW_SCALE: usize = 2; // only needed if using animated tag
 const INPUT_PATH: &str = "images/house.png"; // yes why use variable input when you can hardcode it :5head:
 fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    match args.get(1).map(String::as_str) {
+        Some("extract") => return extract(&args[2..]),
+        Some("analyze") => return analyze(),
+        Some("dot") => return export(&args[2..], "dot", Tileset::save_dot),
+        Some("atlas") => return atlas(&args[2..]),
+        Some("rules") => return export(&args[2..], "rules", Tileset::save_rule_sheet),
+        Some("batch") => return batch(&args[2..]),
+        Some("voxel") => return voxel(&args[2..]),
+        Some("hex") => return hex(&args[2..]),
+        Some("world") => return world(&args[2..]),
+        Some("extend") => return extend(&args[2..]),
+        _ => (),
+    }
+
     let config: Config = get_config();
 
     let &debug = config.get("debug").unwrap_or(&false);
     let &animated = config.get("animated").unwrap_or(&false);
     let &testing = config.get("testing").unwrap_or(&false);
 
-    type Runner = fn() -> WaveFunction;
+    let mut report: Report = Report::default();
+    let (wave_function, mut rng, resumed): (WaveFunction, SolverRng, Option<Resumption>) =
+        match get_option("-r", "--resume") {
+            Some(path) => {
+                // the constraints are part of the checkpoint
+                if constraint_options().next().is_some() {
+                    exit_with("--count, --passable and --symmetry can't be changed when resuming a checkpoint");
+                }
+                let start = Instant::now();
+                let Resumed {
+                    wave_function,
+                    rng,
+                    progress,
+                    start: attempt_start,
+                } = WaveFunction::load_checkpoint(&path).unwrap_or_else(|err| exit_with(err));
+                report.timings.extraction = start.elapsed().as_secs_f64();
+                (wave_function, rng, Some((progress, attempt_start)))
+            }
+            None => {
+                let start = Instant::now();
+                let tileset: Tileset = load_tileset();
+                let shape: (usize, usize) = get_shape();
+                report.timings.extraction = start.elapsed().as_secs_f64();
+                if !testing {
+                    let start = Instant::now();
+                    preflight(&tileset, shape);
+                    report.timings.preflight = start.elapsed().as_secs_f64();
+                }
+                let start = Instant::now();
+                let mut wave_function = WaveFunction::new(shape, tileset);
+                add_constraints(&mut wave_function);
+                report.timings.initialization = start.elapsed().as_secs_f64();
+                (wave_function, get_rng(), None)
+            }
+        };
+
+    type Runner = fn(
+        WaveFunction,
+        &mut SolverRng,
+        Option<Resumption>,
+        &mut Report,
+    ) -> Result<WaveFunction, GaveUp>;
     let runner: Runner = if testing {
         testing_runner
     } else if animated {
         animated_runner
     } else if debug {
         debug_runner
+    } else if get_option("-j", "--threads").is_some() {
+        parallel_runner
     } else {
         default_runner
     };
-    let _res = runner();
-    // FIXME: create png from `_res`
+    let res = runner(wave_function, &mut rng, resumed, &mut report);
+    report.finish(&res);
+    println!("{report}");
+    if let Some(path) = get_option("-R", "--report") {
+        let json =
+            serde_json::to_string_pretty(&report).expect("report is always representable as json");
+        if let Err(err) = fs::write(&path, json) {
+            exit_with(format!("not able to write report: {err}"));
+        }
+    }
+    let res: WaveFunction = res.unwrap_or_else(|gave_up| exit_with(gave_up));
+    if let Some(path) = get_option("-o", "--output") {
+        if let Err(err) = res.save_image(&path) {
+            exit_with(format!("not able to write {path}: {err}"));
+        }
+    }
+    if let Some(path) = get_option("--ids", "--ids") {
+        if let Err(err) = res.save_tile_ids(&path) {
+            exit_with(format!("not able to write {path}: {err}"));
+        }
+    }
+}
+
+/// `-t/--tileset <path>`, `-m/--manifest <path>` or `-s/--sample <path>`, falls back to `INPUT_PATH`
+fn load_tileset() -> Tileset {
+    let samples: Vec<String> = get_options("-s", "--sample");
+    let mut tileset: Tileset = if let Some(path) = get_option("-t", "--tileset") {
+        Tileset::load(&path).unwrap_or_else(|err| exit_with(err))
+    } else if let Some(path) = get_option("-m", "--manifest") {
+        Tileset::from_manifest(&path).unwrap_or_else(|err| exit_with(err))
+    } else if !samples.is_empty() {
+        from_samples(&samples)
+    } else {
+        Tileset::from_png(INPUT_PATH)
+    };
+    apply_edge_adjacency(&mut tileset);
+    tileset
 }
 
-fn default_runner() -> WaveFunction {
-    let mut res: Result<(), ()> = Err(());
-    let mut wave_function: WaveFunction = WaveFunction::from_png(OUTPUT_SHAPE, INPUT_PATH);
-    let mut rng: ThreadRng = thread_rng();
-    let mut counter = 1;
-    while let Err(_) = res {
-        println!("try: {counter}");
-        counter += 1;
+/// `--shape <width>x<height>`, falls back to `OUTPUT_SHAPE`
+fn get_shape() -> (usize, usize) {
+    let Some(shape) = get_option("-S", "--shape") else {
+        return OUTPUT_SHAPE;
+    };
+    parse_size("shape", &shape)
+}
 
-        wave_function = WaveFunction::from_png(OUTPUT_SHAPE, INPUT_PATH);
-        while !wave_function.done() {
-            res = wave_function.collapse(&mut rng);
-            if res.is_err() {
-                break;
+fn parse_size(name: &str, size: &str) -> (usize, usize) {
+    let parsed = size
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+    match parsed {
+        Some((width, height)) if width > 0 && height > 0 => (width, height),
+        _ => exit_with(format!(
+            "invalid {name} {size:?}, expected <width>x<height>"
+        )),
+    }
+}
+
+/// Refuses to start the retry loop if the tileset can never fill `shape`.
+fn preflight(tileset: &Tileset, shape: (usize, usize)) {
+    let analysis = tileset.analyze(shape).unwrap_or_else(|err| exit_with(err));
+    if !analysis.satisfiable {
+        eprintln!("{analysis}");
+        exit_with("the tileset can't fill the output, see the analysis above");
+    }
+    if !analysis.is_healthy() {
+        eprintln!("warning: the tileset has problems, run `tiling-wfc analyze` for details");
+    }
+}
+
+/// `analyze [--shape <width>x<height>]`
+fn analyze() {
+    let analysis: Analysis = load_tileset()
+        .analyze(get_shape())
+        .unwrap_or_else(|err| exit_with(err));
+    println!("{analysis}");
+    if !analysis.satisfiable {
+        process::exit(1);
+    }
+}
+
+/// `dot <graph.dot>` and `rules <sheet.png>`, the tileset is selected as for a normal run
+fn export(args: &[String], command: &str, save: fn(&Tileset, &str) -> Result<(), TilesetError>) {
+    let Some(output) = args.first().filter(|s| !s.starts_with('-')) else {
+        exit_with(format!(
+            "usage: tiling-wfc {command} <output> [tileset options]"
+        ));
+    };
+    let tileset: Tileset = load_tileset();
+    if let Err(err) = save(&tileset, output) {
+        exit_with(err);
+    }
+    println!("wrote {} tiles to {output}", tileset.len());
+}
+
+/// `atlas <atlas.png> [--scale <n>] [--columns <n>] [--grid] [--labels]`, also writes `atlas.json`
+fn atlas(args: &[String]) {
+    let Some(output) = args.first().filter(|s| !s.starts_with('-')) else {
+        exit_with("usage: tiling-wfc atlas <atlas.png> [--scale <n>] [--columns <n>] [--grid] [--labels] [tileset options]");
+    };
+    let number = |long: &str| {
+        get_option(long, long).map(|value| {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .unwrap_or_else(|| exit_with(format!("{long} must be a positive number")))
+        })
+    };
+    let options = AtlasOptions {
+        scale: number("--scale").unwrap_or(1) as u32,
+        columns: number("--columns"),
+        grid: args.iter().any(|s| s.eq("--grid")),
+        labels: args.iter().any(|s| s.eq("--labels")),
+    };
+    let tileset: Tileset = load_tileset();
+    if let Err(err) = tileset.save_atlas(output, options) {
+        exit_with(err);
+    }
+    println!("wrote {} tiles to {output}", tileset.len());
+}
+
+/// One line of the manifest written by `batch`.
+#[derive(Serialize)]
+struct BatchEntry {
+    file: String,
+    seed: u64,
+    attempts: usize,
+    /// seconds
+    time: f64,
+}
+
+/// `batch <count> <directory> [--seed <first>] [--random-seeds]`, also takes the tileset
+/// options, `--shape`, `--max-attempts` (default `DEFAULT_SEED_ATTEMPTS` per seed) and
+/// `--timeout` (per seed)
+fn batch(args: &[String]) {
+    let usage =
+        "usage: tiling-wfc batch <count> <directory> [--seed <first>] [--random-seeds] [options]";
+    let [count, directory, ..] = args else {
+        exit_with(usage);
+    };
+    let count: usize = count
+        .parse()
+        .ok()
+        .filter(|&count| count > 0)
+        .unwrap_or_else(|| exit_with(usage));
+    if directory.starts_with('-') {
+        exit_with(usage);
+    }
+    let random_seeds = args.iter().any(|s| s.eq("--random-seeds"));
+    let first_seed: u64 = get_seed().unwrap_or(0);
+
+    // extracted once and shared by every output
+    let tileset: Arc<Tileset> = Arc::new(load_tileset());
+    let shape: (usize, usize) = get_shape();
+    preflight(&tileset, shape);
+    if let Err(err) = fs::create_dir_all(directory) {
+        exit_with(format!("not able to create {directory}: {err}"));
+    }
+
+    let mut template = WaveFunction::new(shape, tileset);
+    add_constraints(&mut template);
+    let mut batch: Batch = Batch::new(template);
+    let mut limits: Limits = get_limits();
+    limits.max_attempts = limits.max_attempts.or(Some(DEFAULT_SEED_ATTEMPTS));
+    batch.set_limits(limits);
+
+    // small outputs may not have `count` distinct solutions, so give up at some point
+    let max_seeds = count.saturating_mul(10);
+    let mut entries: Vec<BatchEntry> = Vec::new();
+    let mut tried = 0;
+    while entries.len() < count && tried < max_seeds {
+        let seed: u64 = if random_seeds {
+            thread_rng().gen()
+        } else {
+            first_seed.wrapping_add(tried as u64)
+        };
+        tried += 1;
+
+        let output: BatchOutput = match batch.solve(seed) {
+            Ok(Some(output)) => output,
+            Ok(None) => {
+                println!("seed {seed}: duplicate, skipped");
+                continue;
+            }
+            Err(gave_up) => {
+                eprintln!("seed {seed}: {gave_up}");
+                continue;
             }
+        };
+
+        let file = format!("{:04}.png", entries.len());
+        let path = Path::new(directory).join(&file);
+        if let Err(err) = output.wave_function.save_image(&path.to_string_lossy()) {
+            exit_with(format!("not able to write {}: {err}", path.display()));
+        }
+        let time = output.time.as_secs_f64();
+        println!(
+            "seed {seed}: {file}, {} attempts, {time:.3}s",
+            output.attempts
+        );
+        entries.push(BatchEntry {
+            file,
+            seed,
+            attempts: output.attempts,
+            time,
+        });
+    }
+
+    let manifest =
+        serde_json::to_string_pretty(&entries).expect("manifest is always representable as json");
+    let manifest_path = Path::new(directory).join("manifest.json");
+    if let Err(err) = fs::write(&manifest_path, manifest) {
+        exit_with(format!(
+            "not able to write {}: {err}",
+            manifest_path.display()
+        ));
+    }
+    if entries.len() < count {
+        exit_with(format!(
+            "only {} of {count} distinct outputs after trying {tried} seeds",
+            entries.len()
+        ));
+    }
+}
+
+/// `voxel <slice.png>... <directory> [--shape <width>x<height>x<depth>] [--vox <model.vox>]
+/// [--empty <#rrggbb>]`, slices are given from the bottom layer up, also takes `--seed`,
+/// `--max-attempts` and `--timeout`
+fn voxel(args: &[String]) {
+    let positional: Vec<&String> = args.iter().take_while(|s| !s.starts_with('-')).collect();
+    let usage = "usage: tiling-wfc voxel <slice.png>... <directory> [--shape <width>x<height>x<depth>] [--vox <model.vox>] [--empty <#rrggbb>] [--seed <n>] [--max-attempts <n>] [--timeout <seconds>]";
+    let [slices @ .., directory] = positional.as_slice() else {
+        exit_with(usage);
+    };
+    if slices.is_empty() {
+        exit_with(usage);
+    }
+    let shape: (usize, usize, usize) = match get_option("-S", "--shape") {
+        None => VOXEL_SHAPE,
+        Some(shape) => {
+            let dims: Vec<usize> = shape
+                .split('x')
+                .filter_map(|dim| dim.parse().ok())
+                .collect();
+            match dims.as_slice() {
+                &[width, height, depth] if width > 0 && height > 0 && depth > 0 => {
+                    (width, height, depth)
+                }
+                _ => exit_with(format!(
+                    "invalid shape {shape:?}, expected <width>x<height>x<depth>"
+                )),
+            }
+        }
+    };
+    let empty: Option<WfcColor> = get_option("--empty", "--empty")
+        .map(|color| color.parse().unwrap_or_else(|err: String| exit_with(err)));
+
+    let slices: Vec<&str> = slices.iter().map(|s| s.as_str()).collect();
+    let tileset: Arc<VoxelTileset> =
+        Arc::new(VoxelTileset::from_slices(&slices).unwrap_or_else(|err| exit_with(err)));
+    println!("{} voxel tiles", tileset.len());
+
+    let mut rng: SolverRng = get_rng();
+    let res = VoxelWaveFunction::new(shape, tileset).solve(&mut rng, get_limits(), &mut ());
+    let wave_function: VoxelWaveFunction = res.unwrap_or_else(|gave_up| exit_with(gave_up));
+    println!("work: {}", wave_function.stats());
+
+    if let Err(err) = wave_function.save_slices(directory) {
+        exit_with(format!("not able to write slices to {directory}: {err}"));
+    }
+    println!("wrote {} slices to {directory}", shape.2 * TILE_SIZE);
+    if let Some(path) = get_option("--vox", "--vox") {
+        if let Err(err) = wave_function.save_vox(&path, empty) {
+            exit_with(format!("not able to write {path}: {err}"));
+        }
+        println!("wrote {path}");
+    }
+}
+
+/// `hex <map.json|sample.png> <output.png> [--layout offset|axial] [--width <pixels>]`,
+/// also takes `--shape`, `--seed`, `--max-attempts` and `--timeout`
+fn hex(args: &[String]) {
+    let usage = "usage: tiling-wfc hex <map.json|sample.png> <output.png> [--layout offset|axial] [--width <pixels>] [--shape <width>x<height>] [--seed <n>] [--max-attempts <n>] [--timeout <seconds>]";
+    let [sample, output, ..] = args else {
+        exit_with(usage);
+    };
+    if sample.starts_with('-') || output.starts_with('-') {
+        exit_with(usage);
+    }
+    let topology: Hex = match get_option("--layout", "--layout").as_deref() {
+        None | Some("offset") => Hex::offset(),
+        Some("axial") => Hex::axial(),
+        Some(layout) => exit_with(format!(
+            "unknown layout {layout:?}, expected offset or axial"
+        )),
+    };
+    let width: u32 = match get_option("--width", "--width") {
+        Some(width) => width
+            .parse()
+            .ok()
+            .filter(|&width| width > 0)
+            .unwrap_or_else(|| exit_with("--width must be a positive number")),
+        None => HEX_WIDTH,
+    };
+    let shape: (usize, usize) = match get_option("-S", "--shape") {
+        Some(_) => get_shape(),
+        None => HEX_SHAPE,
+    };
+
+    let tileset: Tileset = if sample.ends_with(".json") {
+        Tileset::from_hex_map(sample)
+    } else {
+        Tileset::from_hex_png(sample)
+    }
+    .unwrap_or_else(|err| exit_with(err));
+    println!("{} hex tiles", tileset.len());
+
+    let mut rng: SolverRng = get_rng();
+    let res = WaveFunction::with_topology(shape, tileset, topology).solve(
+        &mut rng,
+        get_limits(),
+        &mut (),
+    );
+    let wave_function: WaveFunction<Hex> = res.unwrap_or_else(|gave_up| exit_with(gave_up));
+    if let Err(err) = wave_function.save_hex_image(output, width) {
+        exit_with(format!("not able to write {output}: {err}"));
+    }
+    println!("wrote {output}");
+}
+
+/// `world <output.png> [--chunks <width>x<height>] [--chunk-size <width>x<height>]`,
+/// generates the chunks row by row from `(0, 0)` like a player walking through them,
+/// also takes the tileset options, `--seed`, `--max-attempts` and `--timeout`
+fn world(args: &[String]) {
+    let usage = "usage: tiling-wfc world <output.png> [--chunks <width>x<height>] [--chunk-size <width>x<height>] [--seed <n>] [--max-attempts <n>] [--timeout <seconds>]";
+    let Some(output) = args.first().filter(|output| !output.starts_with('-')) else {
+        exit_with(usage);
+    };
+    let (chunks_x, chunks_y) = get_option("--chunks", "--chunks")
+        .map_or(WORLD_CHUNKS, |chunks| parse_size("chunk count", &chunks));
+    let chunk_size = get_option("--chunk-size", "--chunk-size")
+        .map_or(CHUNK_SIZE, |size| parse_size("chunk size", &size));
+    let seed: u64 = get_seed().unwrap_or_else(|| thread_rng().gen());
+    println!("world seed: {seed}");
+
+    let mut limits: Limits = get_limits();
+    limits.max_attempts = limits.max_attempts.or(Some(DEFAULT_CHUNK_ATTEMPTS));
+    let mut world =
+        World::new(load_tileset(), chunk_size, seed).unwrap_or_else(|err| exit_with(err));
+    world.set_limits(limits);
+    let start = Instant::now();
+    for y in 0..chunks_y as i64 {
+        for x in 0..chunks_x as i64 {
+            if let Err(gave_up) = world.chunk((x, y)) {
+                exit_with(format!("chunk ({x}, {y}): {gave_up}"));
+            }
+        }
+    }
+    println!(
+        "generated {} chunks in {:.2?}",
+        chunks_x * chunks_y,
+        start.elapsed()
+    );
+
+    let last = (chunks_x as i64 - 1, chunks_y as i64 - 1);
+    if let Err(err) = world.render((0, 0), last).save(output) {
+        exit_with(format!("not able to write {output}: {err}"));
+    }
+    println!("wrote {output}");
+}
+
+/// `extend <tiles.json|output.png> <output.png> [--margin <cells>] [--ids <path>]`,
+/// `--left`, `--right`, `--up` and `--down` override the margin of one side, also takes
+/// the tileset options the output was made with, `--seed`, `--max-attempts` and `--timeout`
+fn extend(args: &[String]) {
+    let usage = "usage: tiling-wfc extend <tiles.json|output.png> <output.png> [--margin <cells>] [--left <cells>] [--right <cells>] [--up <cells>] [--down <cells>] [--ids <path>] [options]";
+    let [input, output, ..] = args else {
+        exit_with(usage);
+    };
+    if input.starts_with('-') || output.starts_with('-') {
+        exit_with(usage);
+    }
+    let cells = |option: &str, default: usize| -> usize {
+        get_option(option, option).map_or(default, |cells| {
+            cells
+                .parse()
+                .unwrap_or_else(|_| exit_with(format!("{option} must be a number of cells")))
+        })
+    };
+    let margin = cells("--margin", 1);
+    let margins = Margins {
+        left: cells("--left", margin),
+        right: cells("--right", margin),
+        up: cells("--up", margin),
+        down: cells("--down", margin),
+    };
+
+    let tileset: Tileset = load_tileset();
+    let tiles: Array2<usize> = if input.ends_with(".json") {
+        load_tile_ids(input)
+    } else {
+        tileset.identify_png(input)
+    }
+    .unwrap_or_else(|err| exit_with(err));
+    if let Some(id) = tiles.iter().find(|&&id| id >= tileset.len()) {
+        exit_with(format!(
+            "{input} has tile {id}, there are only {} tiles",
+            tileset.len()
+        ));
+    }
+
+    let wave_function = WaveFunction::extend(&tiles, tileset, margins)
+        .unwrap_or_else(|_| exit_with("the output can't be extended by these margins"));
+    let mut rng: SolverRng = get_rng();
+    let wave_function: WaveFunction = wave_function
+        .solve(&mut rng, get_limits(), &mut ())
+        .unwrap_or_else(|gave_up| exit_with(gave_up));
+    if let Err(err) = wave_function.save_image(output) {
+        exit_with(format!("not able to write {output}: {err}"));
+    }
+    println!("wrote {output}");
+    if let Some(path) = get_option("--ids", "--ids") {
+        if let Err(err) = wave_function.save_tile_ids(&path) {
+            exit_with(format!("not able to write {path}: {err}"));
+        }
+    }
+}
+
+/// The constraint options that were given.
+fn constraint_options() -> impl Iterator<Item = &'static str> {
+    ["--count", "--passable", "--symmetry"]
+        .into_iter()
+        .filter(|option| get_option(option, option).is_some())
+}
+
+/// `--count`, `--passable` and `--symmetry`
+fn add_constraints(wave_function: &mut WaveFunction) {
+    add_tile_counts(wave_function);
+    add_connectivity(wave_function);
+    add_symmetry(wave_function);
+}
+
+/// `--symmetry horizontal|vertical|both|half-turn` mirrors the output onto itself
+fn add_symmetry(wave_function: &mut WaveFunction) {
+    let Some(symmetry) = get_option("--symmetry", "--symmetry") else {
+        return;
+    };
+    let symmetry: Symmetry = match symmetry.as_str() {
+        "horizontal" => Symmetry::Horizontal,
+        "vertical" => Symmetry::Vertical,
+        "both" => Symmetry::Both,
+        "half-turn" => Symmetry::HalfTurn,
+        _ => exit_with(format!(
+            "unknown symmetry {symmetry:?}, expected horizontal, vertical, both or half-turn"
+        )),
+    };
+    let tileset = wave_function.get_tileset();
+    let counterparts: Vec<Vec<Option<usize>>> = symmetry
+        .mirrors()
+        .iter()
+        .map(|&mirror| tileset.counterparts(mirror))
+        .collect();
+    let unmatched = (0..tileset.len())
+        .filter(|&tile| counterparts.iter().any(|mirrored| mirrored[tile].is_none()))
+        .count();
+    if unmatched > 0 {
+        println!(
+            "{unmatched} of {} tiles have no mirrored counterpart and won't be used",
+            tileset.len()
+        );
+    }
+    wave_function.require_symmetry(symmetry);
+}
+
+/// `--passable <tiles>` requires the comma separated tile ids to form one region, with
+/// `--path <x>,<y>:<x>,<y>` only the two cells have to be connected by them
+fn add_connectivity(wave_function: &mut WaveFunction) {
+    let path = get_option("--path", "--path");
+    let Some(passable) = get_option("--passable", "--passable") else {
+        if path.is_some() {
+            exit_with("--path needs the --passable tiles");
+        }
+        return;
+    };
+    let num_tiles = wave_function.get_tileset().len();
+    let passable: Vec<usize> = passable
+        .split(',')
+        .map(|tile| match tile.trim().parse() {
+            Ok(tile) if tile < num_tiles => tile,
+            _ => exit_with(format!(
+                "invalid passable tile {tile:?}, there are {num_tiles} tiles"
+            )),
+        })
+        .collect();
+    let Some(path) = path else {
+        wave_function.require_connected(&passable);
+        return;
+    };
+
+    let [shape_x, shape_y] = wave_function.shape();
+    let cell = |cell: &str| -> Coordinates {
+        let parsed = cell
+            .split_once(',')
+            .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)));
+        match parsed {
+            Some((x, y)) if x < shape_x && y < shape_y => (x, y),
+            _ => exit_with(format!(
+                "invalid path cell {cell:?}, expected <x>,<y> inside the output"
+            )),
+        }
+    };
+    let Some((from, to)) = path.split_once(':') else {
+        exit_with(format!("invalid path {path:?}, expected <x>,<y>:<x>,<y>"));
+    };
+    wave_function.require_path(&passable, cell(from), cell(to));
+}
+
+/// `--count <tiles>=<count>` limits how many cells hold one of the comma separated
+/// tile ids, the count is a number, `<min>..<max>`, `<min>..` or `..<max>`, and
+/// every bound can be a percentage of the output cells, like `--count 4=..5%`
+fn add_tile_counts(wave_function: &mut WaveFunction) {
+    let counts = get_options("--count", "--count");
+    if counts.is_empty() {
+        return;
+    }
+    let [shape_x, shape_y] = wave_function.shape();
+    let cells = shape_x * shape_y;
+    for count in counts {
+        let invalid = || -> ! {
+            exit_with(format!(
+                "invalid count {count:?}, expected <tiles>=<min>..<max> like 3,4=1..5%"
+            ))
+        };
+        let (tiles, bounds) = count.split_once('=').unwrap_or_else(|| invalid());
+        let tiles: Vec<usize> = tiles
+            .split(',')
+            .map(|tile| tile.trim().parse().unwrap_or_else(|_| invalid()))
+            .collect();
+        if let Some(&tile) = tiles
+            .iter()
+            .find(|&&tile| tile >= wave_function.get_tileset().len())
+        {
+            exit_with(format!(
+                "--count refers to tile {tile}, there are only {} tiles",
+                wave_function.get_tileset().len()
+            ));
+        }
+        // percentages of a range round towards its inside, exact ones to the nearest cell
+        let bound = |bound: &str, round: fn(f64) -> f64| -> usize {
+            match bound.strip_suffix('%') {
+                Some(percent) => {
+                    let percent: f64 = percent.parse().unwrap_or_else(|_| invalid());
+                    round(percent / 100.0 * cells as f64) as usize
+                }
+                None => bound.parse().unwrap_or_else(|_| invalid()),
+            }
+        };
+        let (min, max) = match bounds.split_once("..") {
+            Some((min, max)) => (
+                if min.is_empty() {
+                    0
+                } else {
+                    bound(min, f64::ceil)
+                },
+                if max.is_empty() {
+                    usize::MAX
+                } else {
+                    bound(max, f64::floor)
+                },
+            ),
+            None => {
+                let exact = bound(bounds, f64::round);
+                (exact, exact)
+            }
+        };
+        if min > max {
+            exit_with(format!(
+                "--count {count:?} leaves no whole number of cells between its bounds"
+            ));
+        }
+        wave_function.add_tile_count(TileCount::between(tiles, min, max));
+    }
+}
+
+/// `-E/--edges <tolerance>` switches to adjacency derived from matching tile edges
+fn apply_edge_adjacency(tileset: &mut Tileset) {
+    if let Some(tolerance) = get_option("-E", "--edges") {
+        let tolerance: u8 = tolerance
+            .parse()
+            .unwrap_or_else(|_| exit_with("edge tolerance must be a number from 0 to 255"));
+        if let Err(err) = tileset.use_edge_adjacency(tolerance) {
+            exit_with(err);
+        }
+    }
+}
+
+/// `-s/--sample <path.png[:weight]>`, may be given several times
+fn from_samples(samples: &[String]) -> Tileset {
+    let samples: Vec<(&str, f64)> = samples
+        .iter()
+        .map(|spec| match spec.rsplit_once(':') {
+            Some((path, weight)) if weight.parse::<f64>().is_ok() => {
+                (path, weight.parse().unwrap())
+            }
+            _ => (spec.as_str(), 1.0),
+        })
+        .collect();
+    Tileset::from_pngs(&samples).unwrap_or_else(|err| exit_with(err))
+}
+
+/// `extract <sample.png[:weight]>... | <manifest.json>  <tileset.json|tileset.bin> [-E <tolerance>]`
+fn extract(args: &[String]) {
+    let positional: Vec<String> = args
+        .iter()
+        .take_while(|s| !s.starts_with('-'))
+        .cloned()
+        .collect();
+    let usage = "usage: tiling-wfc extract <sample.png[:weight]>... | <manifest.json> <tileset.json|tileset.bin> [-E <tolerance>]";
+    let [inputs @ .., output] = positional.as_slice() else {
+        exit_with(usage);
+    };
+    if inputs.is_empty() {
+        exit_with(usage);
+    }
+    let mut tileset: Tileset = match inputs {
+        [manifest] if manifest.ends_with(".json") => {
+            Tileset::from_manifest(manifest).unwrap_or_else(|err| exit_with(err))
+        }
+        samples => from_samples(samples),
+    };
+    apply_edge_adjacency(&mut tileset);
+    if let Err(err) = tileset.save(output) {
+        exit_with(err);
+    }
+    println!(
+        "extracted {} tiles from {} into {output}",
+        tileset.len(),
+        inputs.join(", ")
+    );
+}
+
+/// `--seed <number>`, a random seed is picked and printed otherwise
+fn get_rng() -> SolverRng {
+    let seed: u64 = get_seed().unwrap_or_else(|| thread_rng().gen());
+    println!("seed: {seed}");
+    SolverRng::seed_from_u64(seed)
+}
+
+fn get_seed() -> Option<u64> {
+    get_option("--seed", "--seed").map(|seed| {
+        seed.parse()
+            .unwrap_or_else(|_| exit_with("seed must be a non negative number"))
+    })
+}
+
+/// `-c/--checkpoint <path>` and `--checkpoint-every <collapses>` (default 1000)
+fn get_checkpointing() -> Option<Checkpointing> {
+    let path = get_option("-c", "--checkpoint")?;
+    let every = match get_option("--checkpoint-every", "--checkpoint-every") {
+        Some(every) => every
+            .parse()
+            .ok()
+            .filter(|&every| every > 0)
+            .unwrap_or_else(|| exit_with("--checkpoint-every must be a positive number")),
+        None => 1000,
+    };
+    // a saved tileset is only referenced if it is used unchanged
+    let tileset_path =
+        get_option("-t", "--tileset").filter(|_| get_option("-E", "--edges").is_none());
+    Some(Checkpointing {
+        path,
+        every,
+        tileset_path,
+    })
+}
+
+/// `--max-attempts <n>` and `--timeout <seconds>`
+fn get_limits() -> Limits {
+    let max_attempts = get_option("--max-attempts", "--max-attempts").map(|max| {
+        max.parse()
+            .ok()
+            .filter(|&max: &usize| max > 0)
+            .unwrap_or_else(|| exit_with("--max-attempts must be a positive number"))
+    });
+    let timeout = get_option("--timeout", "--timeout").map(|seconds| {
+        seconds
+            .parse()
+            .ok()
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            .unwrap_or_else(|| exit_with("--timeout must be a number of seconds"))
+    });
+    Limits {
+        max_attempts,
+        timeout,
+        cancel: None,
+    }
+}
+
+/// Everything `--report` writes, also printed as a summary after every run.
+#[derive(Default, Serialize)]
+struct Report {
+    attempts: usize,
+    /// why the run gave up, if it did
+    gave_up: Option<String>,
+    /// summed over all attempts
+    stats: SolveStats,
+    timings: Timings,
+    /// how often every tile id appears in the result
+    tile_usage: Vec<usize>,
+}
+
+/// Wall time per phase in seconds.
+#[derive(Default, Serialize)]
+struct Timings {
+    /// loading the tileset or the checkpoint
+    extraction: f64,
+    preflight: f64,
+    /// creating the wave function and its constraints
+    initialization: f64,
+    /// all attempts, setting up the retries included
+    solve: f64,
+}
+
+impl Report {
+    /// Ends a failed attempt and creates the wave function for the next one.
+    #[cfg(feature = "viewer")]
+    fn retry(
+        &mut self,
+        wave_function: WaveFunction,
+        attempts: &mut Attempts,
+        interrupted: Option<GiveUpReason>,
+    ) -> Result<WaveFunction, GaveUp> {
+        self.stats += wave_function.stats();
+        let start = Instant::now();
+        let next = attempts.next(wave_function, interrupted);
+        self.timings.initialization += start.elapsed().as_secs_f64();
+        next
+    }
+
+    fn finish(&mut self, res: &Result<WaveFunction, GaveUp>) {
+        match res {
+            Ok(wave_function) => {
+                self.stats += wave_function.stats();
+                self.tile_usage = wave_function.tile_usage();
+            }
+            // the stats of every attempt are already counted by `retry`
+            Err(gave_up) => {
+                self.gave_up = Some(gave_up.reason.to_string());
+                self.tile_usage = gave_up.best.tile_usage();
+            }
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let used = self.tile_usage.iter().filter(|&&count| count > 0).count();
+        writeln!(f, "attempts: {}", self.attempts)?;
+        writeln!(f, "work: {}", self.stats)?;
+        writeln!(
+            f,
+            "time: extraction {:.3}s, preflight {:.3}s, initialization {:.3}s, solve {:.3}s",
+            self.timings.extraction,
+            self.timings.preflight,
+            self.timings.initialization,
+            self.timings.solve
+        )?;
+        write!(f, "tiles used: {used} of {}", self.tile_usage.len())
+    }
+}
+
+/// Progress of the retry loop a checkpoint was taken in and the state its attempts
+/// start from.
+type Resumption = (Progress, Option<Snapshot>);
+
+/// Starts counting attempts, a run `resumed` from a checkpoint continues its attempt
+/// count and clock and retries from the state its attempts started from.
+fn start_attempts(limits: Limits, resumed: Option<Resumption>) -> Attempts {
+    match resumed {
+        Some((progress, start)) => Attempts::resuming(limits, start, progress),
+        None => Attempts::new(limits),
+    }
+}
+
+/// Prints the number of every attempt as it starts.
+struct AttemptPrinter;
+
+impl Observer for AttemptPrinter {
+    fn on_attempt(&mut self, attempt: usize) {
+        println!("try: {attempt}");
+    }
+}
+
+fn default_runner(
+    wave_function: WaveFunction,
+    rng: &mut SolverRng,
+    resumed: Option<Resumption>,
+    report: &mut Report,
+) -> Result<WaveFunction, GaveUp> {
+    let mut attempts: Attempts = start_attempts(get_limits(), resumed);
+    let start = Instant::now();
+    let res = match get_checkpointing() {
+        Some(checkpointing) => attempts.solve_checkpointed(
+            wave_function,
+            rng,
+            &checkpointing,
+            &mut AttemptPrinter,
+            |err| eprintln!("warning: {err}"),
+        ),
+        None => attempts.solve(wave_function, rng, &mut AttemptPrinter),
+    };
+    report.timings.solve += start.elapsed().as_secs_f64();
+    report.attempts = attempts.count();
+    report.stats += attempts.other_stats();
+    res
+}
+
+/// `-j/--threads <n>` runs attempts on `n` threads, the result only depends on `--seed`
+/// or the resumed checkpoint
+fn parallel_runner(
+    wave_function: WaveFunction,
+    rng: &mut SolverRng,
+    resumed: Option<Resumption>,
+    report: &mut Report,
+) -> Result<WaveFunction, GaveUp> {
+    let threads: usize = get_option("-j", "--threads")
+        .and_then(|threads| threads.parse().ok())
+        .filter(|&threads| threads > 0)
+        .unwrap_or_else(|| exit_with("--threads must be a positive number"));
+    if get_option("-c", "--checkpoint").is_some() {
+        eprintln!("warning: no checkpoints are written when solving on several threads");
+    }
+    // a resumed run continues the attempts before the checkpoint, which count against
+    // the limits, and its attempts start from the same state they did
+    let attempts: Attempts = start_attempts(Limits::default(), resumed);
+    let progress: Progress = attempts.progress();
+    let mut limits: Limits = get_limits();
+    limits.max_attempts = limits
+        .max_attempts
+        .map(|max| max.saturating_sub(progress.attempt - 1).max(1));
+    limits.timeout = limits
+        .timeout
+        .map(|timeout| timeout.saturating_sub(progress.elapsed));
+    let wave_function: WaveFunction = attempts.restart(&wave_function);
+
+    let master_seed: u64 = rng.gen();
+    let start = Instant::now();
+    let res = wave_function.solve_parallel(master_seed, threads, limits);
+    report.timings.solve += start.elapsed().as_secs_f64();
+    match res {
+        Ok(solved) => {
+            println!(
+                "solved by attempt {} of {} on {threads} threads, seed of that attempt: {}",
+                solved.attempt + 1,
+                solved.attempts,
+                solved.seed
+            );
+            report.attempts = solved.attempts;
+            report.stats += solved.other_stats;
+            Ok(solved.wave_function)
+        }
+        Err(gave_up) => {
+            report.attempts = gave_up.attempts;
+            Err(gave_up)
         }
     }
-    wave_function
 }
 
 #[allow(unused_mut)]
-fn debug_runner() -> WaveFunction {
-    animated_runner()
+fn debug_runner(
+    wave_function: WaveFunction,
+    rng: &mut SolverRng,
+    resumed: Option<Resumption>,
+    report: &mut Report,
+) -> Result<WaveFunction, GaveUp> {
+    animated_runner(wave_function, rng, resumed, report)
+}
+
+#[cfg(not(feature = "viewer"))]
+fn animated_runner(
+    wave_function: WaveFunction,
+    rng: &mut SolverRng,
+    resumed: Option<Resumption>,
+    report: &mut Report,
+) -> Result<WaveFunction, GaveUp> {
+    eprintln!("built without the `viewer` feature, running headless");
+    default_runner(wave_function, rng, resumed, report)
 }
 
-fn animated_runner() -> WaveFunction {
-    use wfc::tileset::TILE_SIZE;
+#[cfg(feature = "viewer")]
+fn animated_runner(
+    mut wave_function: WaveFunction,
+    rng: &mut SolverRng,
+    resumed: Option<Resumption>,
+    report: &mut Report,
+) -> Result<WaveFunction, GaveUp> {
+    let [shape_x, shape_y] = wave_function.shape();
     let canvas_shape = (
-        OUTPUT_SHAPE.0 * TILE_SIZE * SHOW_SCALE,
-        OUTPUT_SHAPE.1 * TILE_SIZE * SHOW_SCALE,
+        shape_x * TILE_SIZE * SHOW_SCALE,
+        shape_y * TILE_SIZE * SHOW_SCALE,
     );
     let (mut rl, thread): (RaylibHandle, RaylibThread) = raylib::init()
         .size((canvas_shape.0) as i32, (canvas_shape.1) as i32)
         .title("Non-Tiling WFC")
         .build();
+    let mut canvas: Canvas = Canvas::new(&mut rl, &thread, (shape_x, shape_y), SHOW_SCALE);
 
-    let mut res: Result<(), ()> = Err(());
-    let mut wave_function: WaveFunction = WaveFunction::from_png(OUTPUT_SHAPE, INPUT_PATH);
-    let mut rng: ThreadRng = thread_rng();
-
-    let mut counter = 1;
+    let mut res: Result<(), Contradiction> = Err(Contradiction);
+    // the window can be closed at any time, so there are no limits here
+    let mut attempts: Attempts = start_attempts(Limits::default(), resumed);
 
     while !rl.window_should_close() {
         if rl.is_key_down(KeyboardKey::KEY_SPACE) {
@@ -99,37 +1078,45 @@ fn animated_runner() -> WaveFunction {
         d.clear_background(Color::GRAY);
     }
 
-    while let Err(_) = res {
-        println!("try: {counter}");
-        counter += 1;
-
-        wave_function = WaveFunction::from_png(OUTPUT_SHAPE, INPUT_PATH);
+    while res.is_err() {
+        if report.attempts > 0 {
+            wave_function = report.retry(wave_function, &mut attempts, None)?;
+        }
+        report.attempts = attempts.count();
+        println!("try: {}", report.attempts);
 
+        let start = Instant::now();
+        res = Ok(());
         while !wave_function.done() {
             if rl.window_should_close() {
                 panic!("window was closed");
             }
-            wave_function.show(&mut rl, &thread, SHOW_SCALE);
-            res = wave_function.collapse(&mut rng);
+            wave_function.show(&mut rl, &thread, &mut canvas);
+            res = wave_function.collapse(rng);
             if res.is_err() {
                 break;
             }
         }
+        report.timings.solve += start.elapsed().as_secs_f64();
     }
 
     while !rl.window_should_close() {
-        wave_function.show(&mut rl, &thread, SHOW_SCALE);
+        wave_function.show(&mut rl, &thread, &mut canvas);
     }
 
-    wave_function
+    Ok(wave_function)
 }
 
 #[allow(unused_mut)]
-fn testing_runner() -> WaveFunction {
+fn testing_runner(
+    mut wave_function: WaveFunction,
+    rng: &mut SolverRng,
+    _resumed: Option<Resumption>,
+    report: &mut Report,
+) -> Result<WaveFunction, GaveUp> {
     println!("testing");
-    let mut wave_function: WaveFunction = WaveFunction::from_png(OUTPUT_SHAPE, INPUT_PATH);
-    let mut rng: ThreadRng = thread_rng();
+    report.attempts += 1;
     wave_function.print_tileset();
-    let _res = wave_function.collapse(&mut rng);
-    wave_function
+    let _res = wave_function.collapse(rng);
+    Ok(wave_function)
 }
@@ -1,90 +1,185 @@
-use ndarray::{s, Array2, Array3, ArrayView1, ArrayView2};
-use rand::{rngs::ThreadRng, seq::SliceRandom};
-use raylib::prelude::*;
-
-pub(crate) mod tileset;
+use ndarray::{s, Array2, Array3, ArrayView1};
+use rand::{seq::SliceRandom, Rng};
+use std::{fmt, sync::Arc};
+
+pub mod analysis;
+pub mod batch;
+pub mod checkpoint;
+pub mod connectivity;
+pub mod constraints;
+pub mod extend;
+pub mod hex;
+pub mod limits;
+pub mod observer;
+pub mod parallel;
+pub mod render;
+pub mod snapshot;
+pub mod stats;
+pub mod symmetry;
+pub mod tileset;
+pub mod topology;
+#[cfg(feature = "viewer")]
+pub mod viewer;
+pub mod voxel;
+pub mod world;
+use constraints::Constraints;
+use observer::Observer;
+use stats::SolveStats;
 use tileset::Tileset;
+use topology::{Square, Topology};
 
-use crate::wfc::tileset::{WfcColor, TILE_SIZE};
-
-type Coordinates = (usize, usize);
+/// `(x, y)` position of a cell in the output grid.
+pub type Coordinates = (usize, usize);
 type Entropy = usize;
 type EntropyField = Array2<Entropy>;
 type WaveField = Array3<bool>;
 
-pub struct WaveFunction {
+/// Random number generator whose state can be stored in a checkpoint, see
+/// [`WaveFunction::save_checkpoint`]. Seed it with [`rand::SeedableRng`].
+pub type SolverRng = rand_chacha::ChaCha8Rng;
+
+/// Error returned when a cell is left without any possible tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contradiction;
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a cell has no possible tile left")
+    }
+}
+
+impl std::error::Error for Contradiction {}
+
+/// The output grid together with the set of tiles still possible in every cell.
+///
+/// A wave function is solved by calling [`WaveFunction::collapse`] until
+/// [`WaveFunction::done`] returns `true`, or in one go with [`WaveFunction::run`].
+/// A contradiction leaves the wave function unusable, start over with a fresh one or
+/// take back the collapse that caused it with [`WaveFunction::undo`].
+///
+/// Cells are connected as given by the [`Topology`] `T`, the plain square grid unless
+/// the wave function was created with [`WaveFunction::with_topology`].
+pub struct WaveFunction<T: Topology = Square> {
     done: bool,
-    tileset: Tileset,
+    topology: T,
+    tileset: Arc<Tileset>,
     entropy_field: EntropyField,
     wave_field: WaveField,
     shape: [usize; 2],
+    history: Vec<(Coordinates, usize)>,
+    // every tile removed from a cell, `undo_marks` holds where each collapse started
+    bans: Vec<(Coordinates, usize)>,
+    undo_marks: Vec<usize>,
+    constraints: Constraints,
+    stats: SolveStats,
 }
 
 impl WaveFunction {
+    /// Creates an unobserved wave function of `out_shape` cells over `tileset`.
+    ///
+    /// Cells on the border only allow tiles that were seen on the matching border of the sample.
+    /// Pass an `Arc<Tileset>` to share one tileset between many wave functions.
+    pub fn new(out_shape: (usize, usize), tileset: impl Into<Arc<Tileset>>) -> Self {
+        Self::with_topology(out_shape, tileset, Square)
+    }
+    /// Like [`WaveFunction::new`] without the border constraints, every tile is allowed
+    /// in every cell. For outputs that are part of something larger and continue
+    /// beyond their edges.
+    pub fn without_borders(out_shape: (usize, usize), tileset: impl Into<Arc<Tileset>>) -> Self {
+        let mut wave_function = Self::new(out_shape, tileset);
+        wave_function.wave_field.fill(true);
+        wave_function
+            .entropy_field
+            .fill(wave_function.tileset.len());
+        wave_function
+    }
+    /// Learns a [`Tileset`] from the sample at `path` and creates a wave function over it.
     pub fn from_png(out_shape: (usize, usize), path: &str) -> Self {
-        fn generate_wave_field((shape_x, shape_y): (usize, usize), tileset: &Tileset) -> WaveField {
-            let num_tiles = tileset.len();
-            let mut wave_field: WaveField = Array3::from_elem((shape_x, shape_y, num_tiles), true);
-
-            for x in 0..shape_x {
-                for y in 0..shape_y {
-                    for tile in 0..num_tiles {
-                        if x == 0 {
-                            // left border
-                            wave_field[[x, y, tile]] &= tileset[tile].is_left_border();
-                        }
-                        if x == shape_x - 1 {
-                            // right border
-                            wave_field[[x, y, tile]] &= tileset[tile].is_right_border();
-                        }
-                        if y == 0 {
-                            // upper border
-                            wave_field[[x, y, tile]] &= tileset[tile].is_upper_border();
-                        }
-                        if y == shape_y - 1 {
-                            // lower border
-                            wave_field[[x, y, tile]] &= tileset[tile].is_lower_border();
-                        }
-                    }
-                }
-            }
+        Self::new(out_shape, Tileset::from_png(path))
+    }
+}
 
+impl<T: Topology> WaveFunction<T> {
+    /// Like [`WaveFunction::new`] with the cells connected by `topology`.
+    ///
+    /// Cells without a neighbour in some direction only allow tiles that were seen
+    /// without a neighbour in that direction in the sample.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tileset was learned with a different number of directions, see
+    /// [`Tileset::from_pngs_in`].
+    pub fn with_topology(
+        out_shape: (usize, usize),
+        tileset: impl Into<Arc<Tileset>>,
+        topology: T,
+    ) -> Self {
+        let tileset: Arc<Tileset> = tileset.into();
+        assert_eq!(
+            tileset.directions(),
+            topology.directions(),
+            "the tileset was learned with a topology of other directions"
+        );
+        let (shape_x, shape_y) = out_shape;
+        let shape = [shape_x, shape_y];
+
+        // cells without a neighbour in a direction only allow tiles seen on that border
+        let wave_field: WaveField =
+            Array3::from_shape_fn((shape_x, shape_y, tileset.len()), |(x, y, tile)| {
+                (0..topology.directions()).all(|direction| {
+                    topology.neighbour((x, y), direction, shape).is_some()
+                        || tileset[tile].is_border(direction)
+                })
+            });
+        let entropy_field: EntropyField = Array2::from_shape_fn(out_shape, |(x, y)| {
             wave_field
-        }
-        fn generate_entropy_field(wave_field: &WaveField) -> EntropyField {
-            fn entropy((x, y): (usize, usize), wave_field: &WaveField) -> Entropy {
-                wave_field
-                    .slice(s![x, y, ..])
-                    .iter()
-                    .filter(|&&b| b)
-                    .count()
-            }
-
-            let wave_field_shape: &[usize] = wave_field.shape();
-            let entropy_shape: (usize, usize) = (wave_field_shape[0], wave_field_shape[1]);
-            let entropy_field: EntropyField =
-                Array2::from_shape_fn(entropy_shape, |(x, y)| entropy((x, y), wave_field));
-            entropy_field
-        }
-
-        let tileset: Tileset = Tileset::from_png(path);
-        let wave_field: WaveField = generate_wave_field(out_shape, &tileset);
-        let entropy_field: EntropyField = generate_entropy_field(&wave_field);
-        let temp_shape = entropy_field.shape();
-        let shape = [temp_shape[0], temp_shape[1]];
+                .slice(s![x, y, ..])
+                .iter()
+                .filter(|&&b| b)
+                .count()
+        });
 
         Self {
             done: false,
+            topology,
             tileset,
             entropy_field,
             wave_field,
             shape,
+            history: Vec::new(),
+            bans: Vec::new(),
+            undo_marks: Vec::new(),
+            constraints: Constraints::default(),
+            stats: SolveStats::default(),
         }
     }
+    /// Returns `true` once every cell is collapsed to a single tile.
     pub fn done(&self) -> bool {
         self.done
     }
-    fn get_min_entropy(&mut self, rng: &mut ThreadRng) -> Result<Option<Coordinates>, ()> {
+    /// Returns the `[width, height]` of the output grid.
+    pub fn shape(&self) -> [usize; 2] {
+        self.shape
+    }
+    pub fn get_tileset(&self) -> &Tileset {
+        &self.tileset
+    }
+    /// Returns the tileset as shared between wave functions, cloning it is cheap.
+    pub fn shared_tileset(&self) -> &Arc<Tileset> {
+        &self.tileset
+    }
+    pub fn get_topology(&self) -> &T {
+        &self.topology
+    }
+    /// Every collapse so far in order, as the cell and the tile it was collapsed to.
+    pub fn history(&self) -> &[(Coordinates, usize)] {
+        &self.history
+    }
+    fn get_min_entropy<R: Rng + ?Sized, O: Observer + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        observer: &mut O,
+    ) -> Result<Option<Coordinates>, Contradiction> {
         if self.done() {
             return Ok(None);
         }
@@ -92,13 +187,21 @@ impl WaveFunction {
 
         if min_opt.is_none() {
             self.done = true;
+            observer.on_complete();
             return Ok(None);
         }
 
         let min = min_opt.unwrap();
 
         if min == &0 {
-            return Err(());
+            let (coords, _) = self
+                .entropy_field
+                .indexed_iter()
+                .find(|(_, &entropy)| entropy == 0)
+                .unwrap();
+            self.stats.contradictions += 1;
+            observer.on_contradiction(coords);
+            return Err(Contradiction);
         }
 
         let res = *self
@@ -116,231 +219,252 @@ impl WaveFunction {
         self.tileset.print();
     }
 
-    pub fn collapse(&mut self, rng: &mut ThreadRng) -> Result<(), ()> {
-        fn is_valid_coords(coords: [isize; 2], shape: &[usize]) -> bool {
-            let x = coords[0] as usize;
-            let y = coords[1] as usize;
-            return x < shape[0] && y < shape[1];
-        }
-
-        let coords_opt: Option<Coordinates> = self.get_min_entropy(rng)?;
-        if coords_opt.is_none() {
-            return Ok(());
-        }
+    /// Collapses the cell with the lowest entropy to a random tile and propagates the result.
+    ///
+    /// Returns [`Contradiction`] if propagation ran into a cell without any possible tile.
+    pub fn collapse<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Result<(), Contradiction> {
+        self.collapse_with(rng, &mut ())
+    }
 
-        let (x, y): Coordinates = coords_opt.unwrap();
-        let local_superposition: ArrayView1<bool> = self.wave_field.slice(s![x, y, ..]);
+    /// Like [`WaveFunction::collapse`], reporting every step to `observer`.
+    pub fn collapse_with<R: Rng + ?Sized, O: Observer + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        observer: &mut O,
+    ) -> Result<(), Contradiction> {
+        // a tile count that is still short of its minimum decides the next collapse
+        let below_minimum = if self.done() {
+            None
+        } else {
+            self.apply_constraints(observer)?
+        };
+        let steered = below_minimum.and_then(|index| self.steer_to_minimum(index, rng));
+        let ((x, y), options): (Coordinates, Vec<usize>) = match steered {
+            Some(steered) => steered,
+            None => {
+                let coords_opt: Option<Coordinates> = self.get_min_entropy(rng, observer)?;
+                if coords_opt.is_none() {
+                    return Ok(());
+                }
+                let (x, y): Coordinates = coords_opt.unwrap();
+                let local_superposition: ArrayView1<bool> = self.wave_field.slice(s![x, y, ..]);
+                let options = local_superposition
+                    .indexed_iter()
+                    .filter(|(_, &b)| b)
+                    .map(|(cord, _)| cord)
+                    .collect();
+                ((x, y), options)
+            }
+        };
 
-        let &chosen_tile = local_superposition
-            .indexed_iter()
-            .filter(|(_, &b)| b)
-            .map(|(cord, _)| cord)
-            .collect::<Vec<_>>()
-            .choose(rng)
+        observer.on_select((x, y));
+        self.stats.collapses += 1;
+        let &chosen_tile = options
+            .choose_weighted(rng, |&tile_id| self.tileset[tile_id].get_weight())
             .unwrap();
+        observer.on_collapse((x, y), chosen_tile);
+        self.undo_marks.push(self.bans.len());
+        self.history.push(((x, y), chosen_tile));
         for tile_id in 0..self.tileset.len() {
-            if tile_id != chosen_tile {
-                self.wave_field[[x, y, tile_id]] = false;
+            if tile_id != chosen_tile && self.wave_field[[x, y, tile_id]] {
+                self.ban((x, y), tile_id, observer);
             }
         }
+        observer.on_entropy_change((x, y), 1);
 
-        self.entropy_field[[x, y]] = 1;
-
-        let mut stack: Vec<(usize, usize)> = vec![(x, y)];
-        let shape = self.shape;
+        self.propagate(vec![(x, y)], observer)
+    }
 
-        // push initial neighbours onto the stack
-        {
-            if is_valid_coords([x as isize + 1, y as isize], &shape) && !stack.contains(&(x + 1, y))
-            {
-                stack.push((x + 1, y));
-            }
-            if is_valid_coords([x as isize - 1, y as isize], &shape) && !stack.contains(&(x - 1, y))
-            {
-                stack.push((x - 1, y));
-            }
-            if is_valid_coords([x as isize, y as isize + 1], &shape) && !stack.contains(&(x, y + 1))
-            {
-                stack.push((x, y + 1));
+    /// Removes tiles that don't fit their neighbours anymore, starting at the cells in
+    /// `start` and their neighbours and spreading to the neighbours of every cell that changed.
+    fn propagate<O: Observer + ?Sized>(
+        &mut self,
+        start: Vec<Coordinates>,
+        observer: &mut O,
+    ) -> Result<(), Contradiction> {
+        fn push_neighbours<T: Topology>(
+            coords: Coordinates,
+            topology: &T,
+            shape: [usize; 2],
+            stack: &mut Vec<Coordinates>,
+            on_stack: &mut Array2<bool>,
+        ) {
+            for direction in 0..topology.directions() {
+                if let Some(neighbour) = topology.neighbour(coords, direction, shape) {
+                    if !on_stack[neighbour] {
+                        on_stack[neighbour] = true;
+                        stack.push(neighbour);
+                    }
+                }
             }
-            if is_valid_coords([x as isize, y as isize - 1], &shape) && !stack.contains(&(x, y - 1))
-            {
-                stack.push((x, y - 1));
+        }
+
+        let shape = self.shape;
+        let mut stack: Vec<Coordinates> = Vec::new();
+        let mut on_stack: Array2<bool> = Array2::from_elem((shape[0], shape[1]), false);
+
+        // push initial cells and their neighbours onto the stack
+        for &(x, y) in &start {
+            if !on_stack[[x, y]] {
+                on_stack[[x, y]] = true;
+                stack.push((x, y));
             }
+            push_neighbours((x, y), &self.topology, shape, &mut stack, &mut on_stack);
         }
 
         while let Some((x, y)) = stack.pop() {
+            on_stack[[x, y]] = false;
+            self.stats.propagation_steps += 1;
             let local_entropy = self.entropy_field[[x, y]];
 
             if local_entropy == 0 {
-                return Err(());
+                self.stats.contradictions += 1;
+                observer.on_contradiction((x, y));
+                return Err(Contradiction);
             }
 
             let num_tiles = self.tileset.len();
             let mut local_superposition = vec![true; num_tiles];
 
-            // calculate new `local_superposition`
-            // go up
-            if is_valid_coords([x as isize, y as isize - 1], &shape) {
+            // calculate new `local_superposition`, a tile stays if every neighbour
+            // allows it from the opposite direction
+            for direction in 0..self.topology.directions() {
+                let Some((nx, ny)) = self.topology.neighbour((x, y), direction, shape) else {
+                    continue;
+                };
+                let opposite = self.topology.opposite(direction);
                 let mut temp_superposition = vec![false; num_tiles];
                 for other_tile in 0..num_tiles {
-                    if self.wave_field[[x, y - 1, other_tile]] {
-                        for i in self.tileset[other_tile].get_down() {
-                            if i.is_some() {
-                                temp_superposition[i.unwrap()] = true;
-                            }
+                    if self.wave_field[[nx, ny, other_tile]] {
+                        for &i in self.tileset[other_tile]
+                            .get_neighbours_in(opposite)
+                            .iter()
+                            .flatten()
+                        {
+                            temp_superposition[i] = true;
                         }
                     }
                 }
-                for i in 0..num_tiles {
-                    local_superposition[i] &= temp_superposition[i];
-                }
-            }
-
-            // go down
-            if is_valid_coords([x as isize, y as isize + 1], &shape) {
-                let mut temp_superposition = vec![false; num_tiles];
-                for other_tile in 0..num_tiles {
-                    if self.wave_field[[x, y + 1, other_tile]] {
-                        for i in self.tileset[other_tile].get_up() {
-                            if i.is_some() {
-                                temp_superposition[i.unwrap()] = true;
-                            }
-                        }
-                    }
-                }
-                for i in 0..num_tiles {
-                    local_superposition[i] &= temp_superposition[i];
-                }
-            }
-
-            // go left
-            if is_valid_coords([x as isize - 1, y as isize], &shape) {
-                let mut temp_superposition = vec![false; num_tiles];
-                for other_tile in 0..num_tiles {
-                    if self.wave_field[[x - 1, y, other_tile]] {
-                        for i in self.tileset[other_tile].get_right() {
-                            if i.is_some() {
-                                temp_superposition[i.unwrap()] = true;
-                            }
-                        }
-                    }
-                }
-                for i in 0..num_tiles {
-                    local_superposition[i] &= temp_superposition[i];
-                }
-            }
-
-            // go right
-            if is_valid_coords([x as isize + 1, y as isize], &shape) {
-                let mut temp_superposition = vec![false; num_tiles];
-                for other_tile in 0..num_tiles {
-                    if self.wave_field[[x + 1, y, other_tile]] {
-                        for i in self.tileset[other_tile].get_left() {
-                            if i.is_some() {
-                                temp_superposition[i.unwrap()] = true;
-                            }
-                        }
-                    }
-                }
-                for i in 0..num_tiles {
-                    local_superposition[i] &= temp_superposition[i];
+                for (local, temp) in local_superposition.iter_mut().zip(temp_superposition) {
+                    *local &= temp;
                 }
             }
 
             // if any changes happened add neighbours to stack
-            let mut changes = false;
-            for i in 0..num_tiles {
-                if !local_superposition[i] && self.wave_field[[x, y, i]] {
-                    changes = true;
-                    break;
-                }
-            }
+            let changes = local_superposition
+                .iter()
+                .zip(self.wave_field.slice(s![x, y, ..]))
+                .any(|(&local, &current)| !local && current);
             if changes {
-                if is_valid_coords([x as isize + 1, y as isize], &shape)
-                    && !stack.contains(&(x + 1, y))
-                {
-                    stack.push((x + 1, y));
-                }
-                if is_valid_coords([x as isize - 1, y as isize], &shape)
-                    && !stack.contains(&(x - 1, y))
-                {
-                    stack.push((x - 1, y));
-                }
-                if is_valid_coords([x as isize, y as isize + 1], &shape)
-                    && !stack.contains(&(x, y + 1))
-                {
-                    stack.push((x, y + 1));
-                }
-                if is_valid_coords([x as isize, y as isize - 1], &shape)
-                    && !stack.contains(&(x, y - 1))
-                {
-                    stack.push((x, y - 1));
-                }
+                push_neighbours((x, y), &self.topology, shape, &mut stack, &mut on_stack);
             }
             // update `wave_field` and `entropy_field`
             if changes {
-                let mut count = 0;
-                for i in 0..num_tiles {
-                    self.wave_field[[x, y, i]] &= local_superposition[i];
-                    if self.wave_field[[x, y, i]] {
-                        count += 1;
+                for (i, &local) in local_superposition.iter().enumerate() {
+                    if !local && self.wave_field[[x, y, i]] {
+                        self.ban((x, y), i, observer);
                     }
                 }
-                self.entropy_field[[x, y]] = count;
+                observer.on_entropy_change((x, y), self.entropy_field[[x, y]]);
             }
         }
         Ok(())
     }
 
-    pub fn show(&self, rl: &mut RaylibHandle, thread: &RaylibThread, scale: usize) -> () {
-        fn convert_coords(
-            wave_function_coords: (usize, usize),
-            tile_coords: (usize, usize),
-        ) -> (usize, usize) {
-            let (wf_x, wf_y) = wave_function_coords;
-            let (tile_x, tile_y) = tile_coords;
+    /// Removes every tile `allowed` returns `false` for from the cell at `coords` and
+    /// propagates the change.
+    ///
+    /// Meant for constraints set up before the first collapse, those are kept when
+    /// collapses are undone. Returns [`Contradiction`] if the cell or one it propagates
+    /// to is left without any possible tile.
+    pub fn restrict(
+        &mut self,
+        coords: Coordinates,
+        allowed: impl Fn(usize) -> bool,
+    ) -> Result<(), Contradiction> {
+        let (x, y) = coords;
+        if !self.ban_where(coords, |tile| !allowed(tile), &mut ()) {
+            return Ok(());
+        }
+        if self.entropy_field[[x, y]] == 0 {
+            self.stats.contradictions += 1;
+            return Err(Contradiction);
+        }
+        self.propagate(vec![coords], &mut ())
+    }
 
-            (TILE_SIZE * wf_x + tile_x, TILE_SIZE * wf_y + tile_y)
+    /// Removes every tile `remove` returns `true` for from the cell at `coords`, without
+    /// propagating. Returns whether any tile was removed.
+    fn ban_where<O: Observer + ?Sized>(
+        &mut self,
+        coords: Coordinates,
+        remove: impl Fn(usize) -> bool,
+        observer: &mut O,
+    ) -> bool {
+        let (x, y) = coords;
+        let mut changes = false;
+        for tile in 0..self.tileset.len() {
+            if self.wave_field[[x, y, tile]] && remove(tile) {
+                self.ban(coords, tile, observer);
+                changes = true;
+            }
         }
+        if changes {
+            observer.on_entropy_change(coords, self.entropy_field[[x, y]]);
+        }
+        changes
+    }
+
+    /// Removes `tile` from the cell at `(x, y)` and records it in the undo log.
+    fn ban<O: Observer + ?Sized>(&mut self, (x, y): Coordinates, tile: usize, observer: &mut O) {
+        self.wave_field[[x, y, tile]] = false;
+        self.entropy_field[[x, y]] -= 1;
+        self.bans.push(((x, y), tile));
+        self.stats.bans += 1;
+        observer.on_ban((x, y), tile);
+    }
+
+    /// Collapses cells until the wave function is done or runs into a contradiction.
+    pub fn run<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Result<(), Contradiction> {
+        self.run_with(rng, &mut ())
+    }
 
-        fn wfc_color_to_rl_color(wfc_color: WfcColor) -> Color {
-            let (r, g, b) = wfc_color.get();
-            Color::new(r, g, b, 255)
+    /// Like [`WaveFunction::run`], reporting every step to `observer`.
+    pub fn run_with<R: Rng + ?Sized, O: Observer + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        observer: &mut O,
+    ) -> Result<(), Contradiction> {
+        while !self.done() {
+            self.collapse_with(rng, observer)?;
         }
-        let mut draw_handle = rl.begin_drawing(thread);
-
-        draw_handle.clear_background(Color::GRAY);
-
-        for x in 0..self.shape[0] {
-            for y in 0..self.shape[1] {
-                if self.entropy_field[[x, y]] == 1 {
-                    let tile_id = self
-                        .wave_field
-                        .slice(s![x, y, ..])
-                        .indexed_iter()
-                        .filter(|(_, &b)| b)
-                        .map(|(id, _)| id)
-                        .collect::<Vec<usize>>()[0];
-                    let tile = &self.tileset[tile_id];
-                    let tile_im: ArrayView2<WfcColor> = tile.get_image();
-                    for tile_x in 0..TILE_SIZE {
-                        for tile_y in 0..TILE_SIZE {
-                            let (canvas_x, canvas_y) = convert_coords((x, y), (tile_x, tile_y));
-                            for draw_x in 0..scale {
-                                for draw_y in 0..scale {
-                                    draw_handle.draw_pixel(
-                                        (canvas_y * scale + draw_y) as i32,
-                                        (canvas_x * scale + draw_x) as i32,
-                                        // yes i know x and y are mixxed up, but turns out i'm stupid and this is the easiest fix :)
-                                        wfc_color_to_rl_color(tile_im[[tile_x, tile_y]]),
-                                    )
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+        Ok(())
+    }
+
+    /// Returns the id of the tile at `(x, y)` if that cell is collapsed.
+    pub fn get_tile_id(&self, (x, y): Coordinates) -> Option<usize> {
+        if self.entropy_field[[x, y]] != 1 {
+            return None;
+        }
+        self.wave_field.slice(s![x, y, ..]).iter().position(|&b| b)
+    }
+
+    /// Returns the number of cells collapsed to a single tile.
+    pub fn collapsed_cells(&self) -> usize {
+        self.entropy_field
+            .iter()
+            .filter(|&&entropy| entropy == 1)
+            .count()
+    }
+
+    /// Returns the tile id of every cell, or `None` if the wave function isn't done yet.
+    pub fn get_result(&self) -> Option<Array2<usize>> {
+        if !self.done() {
+            return None;
         }
+        Some(Array2::from_shape_fn(
+            (self.shape[0], self.shape[1]),
+            |coords| self.get_tile_id(coords).unwrap(),
+        ))
     }
 }
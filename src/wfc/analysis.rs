@@ -0,0 +1,222 @@
+use std::fmt;
+
+use ndarray::s;
+
+use super::tileset::{Direction, Tileset, TilesetError};
+use super::{Coordinates, WaveFunction};
+
+/// Health report of a [`Tileset`] for a given output shape, see [`Tileset::analyze`].
+pub struct Analysis {
+    pub num_tiles: usize,
+    pub shape: (usize, usize),
+    /// Tiles without any neighbouring tile on one side. They can only be placed on
+    /// the border of the output on that side.
+    pub dead_ends: Vec<(usize, Direction)>,
+    /// Tiles that can't appear anywhere in an output of `shape`.
+    pub never_appearing: Vec<usize>,
+    /// Strongly connected components of the adjacency graph, largest first.
+    pub components: Vec<Vec<usize>>,
+    /// `false` if the border constraints alone already leave some cell without a
+    /// possible tile, so no output of `shape` exists.
+    pub satisfiable: bool,
+}
+
+impl Analysis {
+    /// Returns `true` if the analysis didn't find anything worth a warning.
+    pub fn is_healthy(&self) -> bool {
+        self.satisfiable
+            && self.dead_ends.is_empty()
+            && self.never_appearing.is_empty()
+            && self.components.len() <= 1
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (width, height) = self.shape;
+        writeln!(f, "{} tiles, output {width}x{height}", self.num_tiles)?;
+        if self.dead_ends.is_empty() {
+            writeln!(f, "dead ends: none")?;
+        } else {
+            writeln!(f, "dead ends:")?;
+            for (tile_id, direction) in &self.dead_ends {
+                writeln!(
+                    f,
+                    "  tile {tile_id} has no neighbour {direction}, it only fits on that border"
+                )?;
+            }
+        }
+        writeln!(f, "never appearing: {:?}", self.never_appearing)?;
+        writeln!(
+            f,
+            "strongly connected components: {}",
+            self.components.len()
+        )?;
+        for component in &self.components {
+            writeln!(f, "  {component:?}")?;
+        }
+        if self.satisfiable {
+            write!(f, "border constraints can be met at {width}x{height}")
+        } else {
+            write!(f, "border constraints can't be met at {width}x{height}")
+        }
+    }
+}
+
+impl Tileset {
+    /// Checks whether the tileset can fill an output of `shape` and reports tiles
+    /// that are likely to cause contradictions.
+    ///
+    /// Satisfiability is decided by propagating the border constraints over an
+    /// empty output, so `satisfiable` being `true` doesn't guarantee that a solve
+    /// succeeds, but `false` guarantees that it never does.
+    ///
+    /// Returns [`TilesetError::Invalid`] for tilesets learned with other directions
+    /// than the four sides.
+    pub fn analyze(&self, shape: (usize, usize)) -> Result<Analysis, TilesetError> {
+        self.require_sides("analysis")?;
+        let mut dead_ends: Vec<(usize, Direction)> = Vec::new();
+        for tile_id in 0..self.len() {
+            for direction in Direction::ALL {
+                if self[tile_id]
+                    .get_neighbours(direction)
+                    .iter()
+                    .all(Option::is_none)
+                {
+                    dead_ends.push((tile_id, direction));
+                }
+            }
+        }
+
+        let mut wave_function = WaveFunction::new(shape, self.clone());
+        let all_cells: Vec<Coordinates> = wave_function
+            .entropy_field
+            .indexed_iter()
+            .map(|(coords, _)| coords)
+            .collect();
+        let satisfiable = !self.is_empty()
+            && wave_function.propagate(all_cells, &mut ()).is_ok()
+            && wave_function
+                .entropy_field
+                .iter()
+                .all(|&entropy| entropy > 0);
+
+        let never_appearing: Vec<usize> = (0..self.len())
+            .filter(|&tile_id| {
+                !satisfiable
+                    || !wave_function
+                        .wave_field
+                        .slice(s![.., .., tile_id])
+                        .iter()
+                        .any(|&b| b)
+            })
+            .collect();
+
+        Ok(Analysis {
+            num_tiles: self.len(),
+            shape,
+            dead_ends,
+            never_appearing,
+            components: self.strongly_connected_components(),
+            satisfiable,
+        })
+    }
+
+    // Kosaraju's algorithm on the graph with an edge from every tile to each of its neighbours
+    fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        let num_tiles = self.len();
+        let mut successors: Vec<Vec<usize>> = vec![Vec::new(); num_tiles];
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); num_tiles];
+        for (tile_id, tile_successors) in successors.iter_mut().enumerate() {
+            for direction in Direction::ALL {
+                for &other_id in self[tile_id].get_neighbours(direction).iter().flatten() {
+                    if !tile_successors.contains(&other_id) {
+                        tile_successors.push(other_id);
+                        predecessors[other_id].push(tile_id);
+                    }
+                }
+            }
+        }
+
+        // order tiles by the time their depth first search finished
+        let mut visited = vec![false; num_tiles];
+        let mut order: Vec<usize> = Vec::with_capacity(num_tiles);
+        for root in 0..num_tiles {
+            if visited[root] {
+                continue;
+            }
+            visited[root] = true;
+            let mut stack: Vec<(usize, usize)> = vec![(root, 0)];
+            while let Some((tile_id, next)) = stack.pop() {
+                if let Some(&other_id) = successors[tile_id].get(next) {
+                    stack.push((tile_id, next + 1));
+                    if !visited[other_id] {
+                        visited[other_id] = true;
+                        stack.push((other_id, 0));
+                    }
+                } else {
+                    order.push(tile_id);
+                }
+            }
+        }
+
+        // collect components on the reversed graph, latest finished first
+        let mut component_of: Vec<Option<usize>> = vec![None; num_tiles];
+        let mut components: Vec<Vec<usize>> = Vec::new();
+        for &root in order.iter().rev() {
+            if component_of[root].is_some() {
+                continue;
+            }
+            let index = components.len();
+            component_of[root] = Some(index);
+            let mut component: Vec<usize> = Vec::new();
+            let mut stack: Vec<usize> = vec![root];
+            while let Some(tile_id) = stack.pop() {
+                component.push(tile_id);
+                for &other_id in &predecessors[tile_id] {
+                    if component_of[other_id].is_none() {
+                        component_of[other_id] = Some(index);
+                        stack.push(other_id);
+                    }
+                }
+            }
+            component.sort_unstable();
+            components.push(component);
+        }
+        components.sort_by_key(|component| std::cmp::Reverse(component.len()));
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wfc::{tileset::fixtures, topology::Diagonals};
+
+    #[test]
+    fn tilesets_with_other_directions_are_rejected_without_panicking() {
+        let mut tileset = fixtures::learn(&fixtures::sample(), &Diagonals);
+        assert!(matches!(
+            tileset.analyze((4, 4)),
+            Err(TilesetError::Invalid(_))
+        ));
+        assert!(matches!(tileset.to_dot(), Err(TilesetError::Invalid(_))));
+        assert!(matches!(
+            tileset.rule_sheet(),
+            Err(TilesetError::Invalid(_))
+        ));
+        assert!(matches!(
+            tileset.use_edge_adjacency(0),
+            Err(TilesetError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn square_tilesets_are_analyzed() {
+        let tileset = fixtures::tileset();
+        let analysis = tileset.analyze((6, 6)).unwrap();
+        assert_eq!(analysis.num_tiles, tileset.len());
+        assert!(analysis.satisfiable);
+        assert!(tileset.to_dot().is_ok());
+    }
+}
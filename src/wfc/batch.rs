@@ -0,0 +1,142 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use ndarray::Array2;
+use rand::SeedableRng;
+
+use super::{
+    limits::{Attempts, GaveUp, Limits},
+    topology::{Square, Topology},
+    SolverRng, WaveFunction,
+};
+
+/// Attempts a seed gets before [`Batch::solve`] gives up on it, unless set otherwise.
+pub const DEFAULT_SEED_ATTEMPTS: usize = 100;
+
+/// A distinct output of a [`Batch`].
+pub struct BatchOutput<T: Topology = Square> {
+    pub wave_function: WaveFunction<T>,
+    pub seed: u64,
+    /// Number of attempts the seed needed, the successful one included.
+    pub attempts: usize,
+    /// Wall time of all those attempts.
+    pub time: Duration,
+}
+
+/// Solves copies of a template wave function with one seed after the other and keeps
+/// the outputs that differ from every earlier one.
+pub struct Batch<T: Topology = Square> {
+    template: WaveFunction<T>,
+    limits: Limits,
+    seen: HashSet<Array2<usize>>,
+}
+
+impl<T: Topology + Clone> Batch<T> {
+    /// Every seed starts from the state `template` is in, with its restrictions and
+    /// constraints.
+    pub fn new(template: WaveFunction<T>) -> Self {
+        Self {
+            template,
+            limits: Limits {
+                max_attempts: Some(DEFAULT_SEED_ATTEMPTS),
+                ..Limits::default()
+            },
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Sets when to give up on a seed, applies to every seed solved from now on on its
+    /// own.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+    /// Number of distinct outputs so far.
+    pub fn distinct(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Solves the template with `seed`, returns `None` if the output is the same as
+    /// one of an earlier seed.
+    pub fn solve(&mut self, seed: u64) -> Result<Option<BatchOutput<T>>, GaveUp<T>> {
+        let start = Instant::now();
+        let mut attempts = Attempts::starting_from(self.limits.clone(), &self.template);
+        let wave_function = attempts.solve(
+            attempts.restart(&self.template),
+            &mut SolverRng::seed_from_u64(seed),
+            &mut (),
+        )?;
+        let time = start.elapsed();
+
+        let tiles = wave_function
+            .get_result()
+            .expect("a solved wave function has every cell collapsed");
+        if !self.seen.insert(tiles) {
+            return Ok(None);
+        }
+        Ok(Some(BatchOutput {
+            wave_function,
+            seed,
+            attempts: attempts.count(),
+            time,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wfc::{constraints::TileCount, tileset::fixtures};
+
+    #[test]
+    fn outputs_are_distinct_and_repeat_their_seed() {
+        // a single row of floors and walls only has a few ways to be filled
+        let template = WaveFunction::new((3, 1), fixtures::floors_and_walls());
+        let mut batch = Batch::new(template);
+        let mut outputs = Vec::new();
+        for seed in 0..40 {
+            if let Some(output) = batch.solve(seed).unwrap() {
+                outputs.push(output);
+            }
+        }
+        assert_eq!(batch.distinct(), outputs.len());
+        assert!(outputs.len() < 40);
+        for (i, output) in outputs.iter().enumerate() {
+            let result = output.wave_function.get_result();
+            assert!(outputs[..i]
+                .iter()
+                .all(|earlier| earlier.wave_function.get_result() != result));
+
+            let mut repeated = WaveFunction::new((3, 1), fixtures::floors_and_walls());
+            repeated
+                .run(&mut SolverRng::seed_from_u64(output.seed))
+                .unwrap();
+            assert_eq!(repeated.get_result(), result);
+            assert_eq!(output.attempts, 1);
+        }
+    }
+
+    #[test]
+    fn attempts_are_counted_up_to_the_successful_one() {
+        // an exact count makes many attempts fail
+        let mut template = WaveFunction::new((8, 8), fixtures::tileset());
+        template.add_tile_count(TileCount::exactly([0], 20));
+        let mut batch = Batch::new(template.fresh_attempt(None));
+        let (seed, attempts) = (0..)
+            .find_map(|seed| {
+                let output = batch.solve(seed).unwrap()?;
+                (output.attempts > 1).then_some((seed, output.attempts))
+            })
+            .unwrap();
+
+        for (max_attempts, solved) in [(attempts - 1, false), (attempts, true)] {
+            let mut batch = Batch::new(template.fresh_attempt(None));
+            batch.set_limits(Limits {
+                max_attempts: Some(max_attempts),
+                ..Limits::default()
+            });
+            assert_eq!(batch.solve(seed).is_ok(), solved);
+        }
+    }
+}
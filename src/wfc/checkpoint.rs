@@ -0,0 +1,642 @@
+use std::{fmt, fs, io, sync::Arc};
+
+use bincode::Options;
+use ndarray::{Array2, Array3};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    constraints::Constraints,
+    limits::{Attempts, GaveUp, Progress},
+    observer::Observer,
+    snapshot::Snapshot,
+    stats::SolveStats,
+    tileset::{Direction, TilesetData, TilesetError},
+    topology::{Square, Topology},
+    Coordinates, SolverRng, Tileset, WaveFunction,
+};
+
+/// Error returned when saving or loading a checkpoint fails.
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+    /// The referenced or embedded tileset couldn't be loaded.
+    Tileset(TilesetError),
+    /// The data was read but doesn't describe a valid wave function.
+    Invalid(String),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(err) => write!(f, "not able to access checkpoint file: {err}"),
+            CheckpointError::Json(err) => write!(f, "not able to parse checkpoint json: {err}"),
+            CheckpointError::Binary(err) => {
+                write!(f, "not able to parse binary checkpoint: {err}")
+            }
+            CheckpointError::Tileset(err) => write!(f, "checkpoint tileset: {err}"),
+            CheckpointError::Invalid(reason) => write!(f, "invalid checkpoint: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<io::Error> for CheckpointError {
+    fn from(err: io::Error) -> Self {
+        CheckpointError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for CheckpointError {
+    fn from(err: serde_json::Error) -> Self {
+        CheckpointError::Json(err)
+    }
+}
+
+impl From<bincode::Error> for CheckpointError {
+    fn from(err: bincode::Error) -> Self {
+        CheckpointError::Binary(err)
+    }
+}
+
+impl From<TilesetError> for CheckpointError {
+    fn from(err: TilesetError) -> Self {
+        CheckpointError::Tileset(err)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TilesetSource {
+    Embedded(TilesetData),
+    /// Path of a tileset written by [`Tileset::save`], with the digest of the tileset
+    /// the checkpoint was taken with.
+    Path {
+        path: String,
+        digest: u64,
+    },
+}
+
+/// Everything [`WaveFunction::load_checkpoint`] reads back.
+pub struct Resumed<T: Topology = Square> {
+    pub wave_function: WaveFunction<T>,
+    /// The generator to continue solving with.
+    pub rng: SolverRng,
+    /// Attempt and time of the retry loop when the checkpoint was taken.
+    pub progress: Progress,
+    /// State every attempt of the retry loop starts from, `None` for a blank wave
+    /// function. Continue the loop with [`Attempts::resuming`].
+    pub start: Option<Snapshot>,
+}
+
+/// Where and how often [`Attempts::solve_checkpointed`] writes checkpoints.
+#[derive(Debug, Clone)]
+pub struct Checkpointing {
+    /// Written as JSON if it ends in `.json` and in a binary format otherwise.
+    pub path: String,
+    /// Number of collapses between two checkpoints, must be positive.
+    pub every: usize,
+    /// Tileset file to refer to instead of embedding the tileset, see
+    /// [`WaveFunction::save_checkpoint`].
+    pub tileset_path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CheckpointData {
+    shape: [usize; 2],
+    num_tiles: usize,
+    // see `Topology::name`
+    topology: String,
+    tileset: TilesetSource,
+    state: StateData,
+    start: Option<StateData>,
+    rng: SolverRng,
+    stats: SolveStats,
+    constraints: Constraints,
+    progress: Progress,
+}
+
+// the fields of a `Snapshot`
+#[derive(Serialize, Deserialize)]
+struct StateData {
+    done: bool,
+    // flattened in `[x, y, tile]` order
+    wave_field: Vec<bool>,
+    // flattened in `[x, y]` order
+    entropy_field: Vec<usize>,
+    history: Vec<(Coordinates, usize)>,
+    // undo log, see `WaveFunction::undo`
+    bans: Vec<(Coordinates, usize)>,
+    undo_marks: Vec<usize>,
+}
+
+impl From<Snapshot> for StateData {
+    fn from(snapshot: Snapshot) -> Self {
+        Self {
+            done: snapshot.done,
+            wave_field: snapshot.wave_field.into_iter().collect(),
+            entropy_field: snapshot.entropy_field.into_iter().collect(),
+            history: snapshot.history,
+            bans: snapshot.bans,
+            undo_marks: snapshot.undo_marks,
+        }
+    }
+}
+
+impl StateData {
+    fn into_snapshot(
+        self,
+        shape: [usize; 2],
+        num_tiles: usize,
+    ) -> Result<Snapshot, CheckpointError> {
+        let [shape_x, shape_y] = shape;
+        let wave_field = Array3::from_shape_vec((shape_x, shape_y, num_tiles), self.wave_field)
+            .map_err(|_| {
+                CheckpointError::Invalid("wave field doesn't match the shape".to_string())
+            })?;
+        let entropy_field = Array2::from_shape_vec((shape_x, shape_y), self.entropy_field)
+            .map_err(|_| {
+                CheckpointError::Invalid("entropy field doesn't match the shape".to_string())
+            })?;
+        for ((x, y), &entropy) in entropy_field.indexed_iter() {
+            let possible = (0..num_tiles)
+                .filter(|&tile| wave_field[[x, y, tile]])
+                .count();
+            if possible != entropy {
+                return Err(CheckpointError::Invalid(format!(
+                    "entropy of cell ({x}, {y}) is {entropy} but {possible} tiles are possible"
+                )));
+            }
+        }
+        if let Some(((x, y), tile)) = self
+            .history
+            .iter()
+            .chain(&self.bans)
+            .find(|&&((x, y), tile)| x >= shape_x || y >= shape_y || tile >= num_tiles)
+        {
+            return Err(CheckpointError::Invalid(format!(
+                "history refers to tile {tile} at cell ({x}, {y}), which doesn't exist"
+            )));
+        }
+        let marks_in_order = self.undo_marks.windows(2).all(|pair| pair[0] <= pair[1]);
+        // undoing sets every logged ban back to possible, so it can't be possible already
+        let bans_applied = self
+            .bans
+            .iter()
+            .all(|&((x, y), tile)| !wave_field[[x, y, tile]]);
+        if self.undo_marks.len() != self.history.len()
+            || !marks_in_order
+            || self
+                .undo_marks
+                .last()
+                .is_some_and(|&mark| mark > self.bans.len())
+            || !bans_applied
+        {
+            return Err(CheckpointError::Invalid(
+                "undo log doesn't match the history".to_string(),
+            ));
+        }
+        Ok(Snapshot {
+            done: self.done,
+            entropy_field,
+            wave_field,
+            history: self.history,
+            bans: self.bans,
+            undo_marks: self.undo_marks,
+        })
+    }
+}
+
+// FNV-1a over the stored form of the tileset, stable across platforms and versions
+fn digest(tileset: &Tileset) -> u64 {
+    let bytes = bincode::DefaultOptions::new()
+        .serialize(&TilesetData::from(tileset))
+        .expect("tilesets are always representable in the binary format");
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl WaveFunction {
+    /// Reads a checkpoint of a wave function on the plain square grid, see
+    /// [`WaveFunction::load_checkpoint_with_topology`].
+    pub fn load_checkpoint(path: &str) -> Result<Resumed, CheckpointError> {
+        Self::load_checkpoint_with_topology(path, Square)
+    }
+}
+
+impl<T: Topology> WaveFunction<T> {
+    /// Writes the complete solver state together with `rng` and the progress and start
+    /// state of the retry loop in `attempts` to `path`, as JSON if the path ends in
+    /// `.json` and in a binary format otherwise. The constraints and the topology are
+    /// part of the state.
+    ///
+    /// The tileset is embedded unless `tileset_path` points to a file written by
+    /// [`Tileset::save`] that holds the same tileset, loading fails if that file changes
+    /// afterwards. Like those files only tilesets with the four sides as directions can
+    /// be stored. Continuing from
+    /// [`WaveFunction::load_checkpoint`] gives exactly the same result as a run that
+    /// was never interrupted, which also makes a checkpoint taken shortly before a
+    /// contradiction a reproduction of it.
+    pub fn save_checkpoint(
+        &self,
+        path: &str,
+        rng: &SolverRng,
+        tileset_path: Option<&str>,
+        attempts: &Attempts<T>,
+    ) -> Result<(), CheckpointError> {
+        if self.tileset.directions() != Direction::ALL.len() {
+            return Err(CheckpointError::Invalid(format!(
+                "only tilesets with the four sides as directions can be stored, this one has {}",
+                self.tileset.directions()
+            )));
+        }
+        let data = CheckpointData {
+            shape: self.shape,
+            num_tiles: self.tileset.len(),
+            topology: self.topology.name().to_string(),
+            tileset: match tileset_path {
+                Some(tileset_path) => TilesetSource::Path {
+                    path: tileset_path.to_string(),
+                    digest: digest(self.get_tileset()),
+                },
+                None => TilesetSource::Embedded(TilesetData::from(self.get_tileset())),
+            },
+            state: StateData::from(self.snapshot()),
+            start: attempts.start().cloned().map(StateData::from),
+            rng: rng.clone(),
+            stats: self.stats,
+            constraints: self.constraints.clone(),
+            progress: attempts.progress(),
+        };
+        if path.ends_with(".json") {
+            fs::write(path, serde_json::to_string(&data)?)?;
+        } else {
+            fs::write(path, bincode::DefaultOptions::new().serialize(&data)?)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a checkpoint written by [`WaveFunction::save_checkpoint`] on a grid with
+    /// `topology`. Fails if it was taken on a different grid.
+    pub fn load_checkpoint_with_topology(
+        path: &str,
+        topology: T,
+    ) -> Result<Resumed<T>, CheckpointError> {
+        let data: CheckpointData = if path.ends_with(".json") {
+            serde_json::from_str(&fs::read_to_string(path)?)?
+        } else {
+            bincode::DefaultOptions::new().deserialize(&fs::read(path)?)?
+        };
+        if data.topology != topology.name() {
+            return Err(CheckpointError::Invalid(format!(
+                "the checkpoint was taken on a {} grid, not on a {} one",
+                data.topology,
+                topology.name()
+            )));
+        }
+
+        let tileset = match data.tileset {
+            TilesetSource::Embedded(tileset) => Tileset::try_from(tileset)?,
+            TilesetSource::Path {
+                path,
+                digest: saved,
+            } => {
+                let tileset = Tileset::load(&path)?;
+                if digest(&tileset) != saved {
+                    return Err(CheckpointError::Invalid(format!(
+                        "the tileset in {path} changed since the checkpoint was taken"
+                    )));
+                }
+                tileset
+            }
+        };
+        if tileset.len() != data.num_tiles {
+            return Err(CheckpointError::Invalid(format!(
+                "the tileset has {} tiles, the checkpoint was taken with {}",
+                tileset.len(),
+                data.num_tiles
+            )));
+        }
+
+        let state = data.state.into_snapshot(data.shape, data.num_tiles)?;
+        let start = data
+            .start
+            .map(|start| start.into_snapshot(data.shape, data.num_tiles))
+            .transpose()?;
+        if !data.constraints.fit(data.shape, data.num_tiles) {
+            return Err(CheckpointError::Invalid(
+                "constraints refer to tiles or cells that don't exist".to_string(),
+            ));
+        }
+
+        let wave_function = WaveFunction {
+            done: state.done,
+            topology,
+            tileset: Arc::new(tileset),
+            entropy_field: state.entropy_field,
+            wave_field: state.wave_field,
+            shape: data.shape,
+            history: state.history,
+            bans: state.bans,
+            undo_marks: state.undo_marks,
+            constraints: data.constraints,
+            stats: data.stats,
+        };
+        Ok(Resumed {
+            wave_function,
+            rng: data.rng,
+            progress: data.progress,
+            start,
+        })
+    }
+}
+
+impl<T: Topology + Clone> Attempts<T> {
+    /// Like [`Attempts::solve`], writing a checkpoint of the running attempt every
+    /// `checkpointing.every` collapses. A checkpoint that can't be written is handed to
+    /// `on_error` and the solve goes on.
+    pub fn solve_checkpointed<O: Observer + ?Sized>(
+        &mut self,
+        wave_function: WaveFunction<T>,
+        rng: &mut SolverRng,
+        checkpointing: &Checkpointing,
+        observer: &mut O,
+        mut on_error: impl FnMut(CheckpointError),
+    ) -> Result<WaveFunction<T>, GaveUp<T>> {
+        self.solve_with(
+            wave_function,
+            rng,
+            observer,
+            |wave_function, rng, attempts| {
+                if wave_function.history().len() % checkpointing.every != 0 {
+                    return;
+                }
+                if let Err(err) = wave_function.save_checkpoint(
+                    &checkpointing.path,
+                    rng,
+                    checkpointing.tileset_path.as_deref(),
+                    attempts,
+                ) {
+                    on_error(err);
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, sync::Arc, time::Duration};
+
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::wfc::{
+        constraints::TileCount,
+        limits::{Attempts, Limits},
+        tileset::{fixtures, Tile},
+        topology::Periodic,
+    };
+
+    const SHAPE: (usize, usize) = (10, 10);
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir()
+            .join(format!("tiling-wfc-{}-{name}", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    // the first seed whose first attempt succeeds, so one attempt covers the whole run
+    fn successful_seed(tileset: &Arc<Tileset>) -> u64 {
+        (0..)
+            .find(|&seed| {
+                let mut wave_function = WaveFunction::new(SHAPE, tileset.clone());
+                wave_function
+                    .run(&mut SolverRng::seed_from_u64(seed))
+                    .is_ok()
+            })
+            .unwrap()
+    }
+
+    fn resume_gives_same_result(path: &str) {
+        let tileset = Arc::new(fixtures::tileset());
+        let seed = successful_seed(&tileset);
+        let mut uninterrupted = WaveFunction::new(SHAPE, tileset.clone());
+        uninterrupted
+            .run(&mut SolverRng::seed_from_u64(seed))
+            .unwrap();
+
+        let mut rng = SolverRng::seed_from_u64(seed);
+        let mut interrupted = WaveFunction::new(SHAPE, tileset);
+        for _ in 0..uninterrupted.history().len() / 2 {
+            interrupted.collapse(&mut rng).unwrap();
+        }
+        let attempts = Attempts::new(Limits::default());
+        interrupted
+            .save_checkpoint(path, &rng, None, &attempts)
+            .unwrap();
+        drop(interrupted);
+
+        let Resumed {
+            mut wave_function,
+            mut rng,
+            ..
+        } = WaveFunction::load_checkpoint(path).unwrap();
+        fs::remove_file(path).unwrap();
+        wave_function.run(&mut rng).unwrap();
+        assert_eq!(wave_function.get_result(), uninterrupted.get_result());
+        assert_eq!(wave_function.history(), uninterrupted.history());
+    }
+
+    #[test]
+    fn resume_from_json_gives_same_result_as_uninterrupted_run() {
+        resume_gives_same_result(&temp_path("resume.json"));
+    }
+
+    #[test]
+    fn resume_from_binary_gives_same_result_as_uninterrupted_run() {
+        resume_gives_same_result(&temp_path("resume.bin"));
+    }
+
+    #[test]
+    fn constraints_and_progress_are_restored() {
+        let path = temp_path("constraints.json");
+        let mut wave_function = WaveFunction::new(SHAPE, fixtures::tileset());
+        wave_function.add_tile_count(TileCount::between([0, 1], 3, 40));
+        let progress = Progress {
+            attempt: 4,
+            elapsed: Duration::from_millis(1500),
+        };
+        let attempts = Attempts::resuming(Limits::default(), None, progress);
+        wave_function
+            .save_checkpoint(&path, &SolverRng::seed_from_u64(0), None, &attempts)
+            .unwrap();
+
+        let resumed = WaveFunction::load_checkpoint(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            resumed.wave_function.tile_counts(),
+            wave_function.tile_counts()
+        );
+        assert_eq!(resumed.progress.attempt, progress.attempt);
+        assert!(resumed.progress.elapsed >= progress.elapsed);
+        assert!(resumed.start.is_none());
+    }
+
+    // drives the attempts like `WaveFunction::solve`, returning early once `stop` is true
+    fn solve_until(
+        mut wave_function: WaveFunction,
+        rng: &mut SolverRng,
+        attempts: &mut Attempts,
+        stop: impl Fn(&WaveFunction, &Attempts) -> bool,
+    ) -> WaveFunction {
+        loop {
+            while !wave_function.done() {
+                if stop(&wave_function, attempts) || wave_function.collapse(rng).is_err() {
+                    break;
+                }
+            }
+            if wave_function.done() || stop(&wave_function, attempts) {
+                return wave_function;
+            }
+            wave_function = attempts.next(wave_function, None).unwrap();
+        }
+    }
+
+    #[test]
+    fn resumed_retries_start_where_the_uninterrupted_ones_do() {
+        let path = temp_path("retry.json");
+        // the tile count makes attempts run into contradictions
+        let mut start = WaveFunction::new((8, 8), fixtures::tileset());
+        start.add_tile_count(TileCount::exactly([0], 20));
+        start.collapse(&mut SolverRng::seed_from_u64(0)).unwrap();
+        // stops a few collapses into the second attempt
+        let solve = |seed, interrupt: bool| {
+            let mut attempts = Attempts::starting_from(Limits::default(), &start);
+            let mut rng = SolverRng::seed_from_u64(seed);
+            let solved = solve_until(
+                attempts.restart(&start),
+                &mut rng,
+                &mut attempts,
+                |wave_function, attempts| {
+                    interrupt && attempts.count() == 2 && wave_function.history().len() == 4
+                },
+            );
+            (solved, rng, attempts)
+        };
+        // the second attempt has to fail after the checkpoint for the retry to matter
+        let seed = (0..)
+            .find(|&seed| solve(seed, false).2.count() > 2 && !solve(seed, true).0.done())
+            .unwrap();
+        let (uninterrupted, _, attempts) = solve(seed, false);
+        let count = attempts.count();
+
+        let (interrupted, rng, attempts) = solve(seed, true);
+        interrupted
+            .save_checkpoint(&path, &rng, None, &attempts)
+            .unwrap();
+        let Resumed {
+            wave_function,
+            mut rng,
+            progress,
+            start,
+        } = WaveFunction::load_checkpoint(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let mut attempts = Attempts::resuming(Limits::default(), start, progress);
+        let resumed = solve_until(wave_function, &mut rng, &mut attempts, |_, _| false);
+        assert_eq!(resumed.get_result(), uninterrupted.get_result());
+        assert_eq!(attempts.count(), count);
+    }
+
+    #[test]
+    fn changed_tileset_files_are_rejected() {
+        let tileset_path = temp_path("referenced.json");
+        let path = temp_path("referenced-checkpoint.json");
+        let tileset = fixtures::tileset();
+        tileset.save(&tileset_path).unwrap();
+        let wave_function = WaveFunction::new(SHAPE, tileset.clone());
+        wave_function
+            .save_checkpoint(
+                &path,
+                &SolverRng::seed_from_u64(0),
+                Some(&tileset_path),
+                &Attempts::new(Limits::default()),
+            )
+            .unwrap();
+        let unchanged = WaveFunction::load_checkpoint(&path);
+
+        // same number of tiles, but with other weights
+        let reweighted = (0..tileset.len())
+            .map(|id| {
+                let tile = &tileset[id];
+                let neighbours = Direction::ALL
+                    .iter()
+                    .map(|&direction| tile.get_neighbours(direction).clone())
+                    .collect();
+                Tile::new(
+                    tile.get_image().to_owned(),
+                    tile.get_weight() * 2.0,
+                    neighbours,
+                )
+            })
+            .collect();
+        Tileset::from_tiles(reweighted).save(&tileset_path).unwrap();
+        let changed = WaveFunction::load_checkpoint(&path);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&tileset_path).unwrap();
+        assert!(unchanged.is_ok());
+        assert!(matches!(changed, Err(CheckpointError::Invalid(_))));
+    }
+
+    #[test]
+    fn bans_that_were_not_applied_are_rejected() {
+        let path = temp_path("bans.json");
+        let mut wave_function = WaveFunction::new(SHAPE, fixtures::tileset());
+        wave_function
+            .collapse(&mut SolverRng::seed_from_u64(0))
+            .unwrap();
+        wave_function
+            .save_checkpoint(
+                &path,
+                &SolverRng::seed_from_u64(0),
+                None,
+                &Attempts::new(Limits::default()),
+            )
+            .unwrap();
+
+        let mut data: CheckpointData =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let ((x, y), tile) = data.state.bans[0];
+        data.state.wave_field[(x * SHAPE.1 + y) * data.num_tiles + tile] = true;
+        data.state.entropy_field[x * SHAPE.1 + y] += 1;
+        fs::write(&path, serde_json::to_string(&data).unwrap()).unwrap();
+        let res = WaveFunction::load_checkpoint(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(res, Err(CheckpointError::Invalid(_))));
+    }
+
+    #[test]
+    fn topology_is_checked_on_load() {
+        let path = temp_path("periodic.json");
+        let tileset = fixtures::learn(&fixtures::sample(), &Periodic);
+        let wave_function = WaveFunction::with_topology(SHAPE, tileset, Periodic);
+        wave_function
+            .save_checkpoint(
+                &path,
+                &SolverRng::seed_from_u64(0),
+                None,
+                &Attempts::new(Limits::default()),
+            )
+            .unwrap();
+
+        let square = WaveFunction::load_checkpoint(&path);
+        let periodic = WaveFunction::load_checkpoint_with_topology(&path, Periodic);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(square, Err(CheckpointError::Invalid(_))));
+        assert!(periodic.is_ok());
+    }
+}
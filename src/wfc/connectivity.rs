@@ -0,0 +1,355 @@
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use super::{observer::Observer, topology::Topology, Contradiction, Coordinates, WaveFunction};
+
+/// Passable cells that have to be connected, see [`WaveFunction::require_connected`]
+/// and [`WaveFunction::require_path`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Connectivity {
+    // indexed by tile id
+    passable: Vec<bool>,
+    // all passable cells form one region if `None`, otherwise only these two have to
+    // be connected
+    path: Option<(Coordinates, Coordinates)>,
+}
+
+impl Connectivity {
+    pub(crate) fn fits(&self, [shape_x, shape_y]: [usize; 2], num_tiles: usize) -> bool {
+        let inside = |(x, y): Coordinates| x < shape_x && y < shape_y;
+        self.passable.len() == num_tiles
+            && self
+                .path
+                .is_none_or(|(from, to)| inside(from) && inside(to))
+    }
+}
+
+// what a cell can still become
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Passage {
+    Blocked,
+    Maybe,
+    Open,
+}
+
+impl<T: Topology> WaveFunction<T> {
+    /// Requires the cells holding one of the `passable` tiles to form a single region,
+    /// where cells are connected to their neighbours in the topology.
+    ///
+    /// Once some cell is sure to be passable, cells that can't reach it anymore are
+    /// made impassable and cells the only way between passable cells leads through are
+    /// made passable. Passable cells that can't reach each other anymore are a
+    /// [`Contradiction`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `passable` contains ids that aren't in the tileset.
+    pub fn require_connected(&mut self, passable: &[usize]) {
+        let passable = self.passable_tiles(passable);
+        self.constraints.connectivity.push(Connectivity {
+            passable,
+            path: None,
+        });
+    }
+
+    /// Requires `from` and `to` to hold `passable` tiles and to be connected by
+    /// passable cells, other passable regions may exist next to theirs. Checked like
+    /// [`WaveFunction::require_connected`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `passable` contains ids that aren't in the tileset or a cell is
+    /// outside of the output.
+    pub fn require_path(&mut self, passable: &[usize], from: Coordinates, to: Coordinates) {
+        let [shape_x, shape_y] = self.shape;
+        assert!(
+            [from, to].iter().all(|&(x, y)| x < shape_x && y < shape_y),
+            "path ends must be inside the output"
+        );
+        let passable = self.passable_tiles(passable);
+        self.constraints.connectivity.push(Connectivity {
+            passable,
+            path: Some((from, to)),
+        });
+    }
+
+    fn passable_tiles(&self, passable: &[usize]) -> Vec<bool> {
+        assert!(
+            passable.iter().all(|&tile| tile < self.tileset.len()),
+            "passable tiles must be in the tileset"
+        );
+        (0..self.tileset.len())
+            .map(|tile| passable.contains(&tile))
+            .collect()
+    }
+
+    /// Removes passable tiles where they could only form a separate region, and makes
+    /// the ends of paths and the cells they can't be connected without passable.
+    /// Returns the cells that changed.
+    pub(crate) fn apply_connectivity<O: Observer + ?Sized>(
+        &mut self,
+        connectivity: &Connectivity,
+        observer: &mut O,
+    ) -> Result<Vec<Coordinates>, Contradiction> {
+        let passable = |tile: usize| connectivity.passable[tile];
+        let mut changed: Vec<Coordinates> = Vec::new();
+        if let Some((from, to)) = connectivity.path {
+            for end in [from, to] {
+                if self.ban_where(end, |tile| !passable(tile), observer) {
+                    changed.push(end);
+                }
+            }
+        }
+
+        let passages: Array2<Passage> =
+            Array2::from_shape_fn(self.entropy_field.dim(), |(x, y)| {
+                let (mut any, mut all) = (false, true);
+                for tile in (0..self.tileset.len()).filter(|&tile| self.wave_field[[x, y, tile]]) {
+                    any |= passable(tile);
+                    all &= passable(tile);
+                }
+                match (any, all) {
+                    (false, _) => Passage::Blocked,
+                    (true, true) => Passage::Open,
+                    (true, false) => Passage::Maybe,
+                }
+            });
+
+        // cells that have to be passable, and so have to stay connected to each other
+        let terminals: Array2<bool> = match connectivity.path {
+            Some((from, to)) => {
+                Array2::from_shape_fn(passages.dim(), |coords| coords == from || coords == to)
+            }
+            None => passages.map(|&passage| passage == Passage::Open),
+        };
+        let Some(root) = terminals
+            .indexed_iter()
+            .find(|(_, &terminal)| terminal)
+            .map(|(coords, _)| coords)
+        else {
+            return Ok(changed);
+        };
+        let (reached, cuts) = self.search(&passages, &terminals, root);
+        // a terminal the others can't reach
+        let separated = terminals
+            .indexed_iter()
+            .find(|&(coords, &terminal)| {
+                terminal && !(reached[coords] && passages[coords] != Passage::Blocked)
+            })
+            .map(|(coords, _)| coords);
+        if separated.is_some() {
+            return Err(self.constraint_contradiction(separated, observer));
+        }
+
+        if connectivity.path.is_none() {
+            // passable cells out of reach would form a region of their own
+            for (coords, &passage) in passages.indexed_iter() {
+                if passage == Passage::Maybe && !reached[coords] {
+                    self.ban_where(coords, passable, observer);
+                    changed.push(coords);
+                }
+            }
+        }
+        for cut in cuts {
+            if passages[cut] == Passage::Maybe {
+                self.ban_where(cut, |tile| !passable(tile), observer);
+                changed.push(cut);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Searches the cells that aren't blocked from `root`, which has to be a terminal.
+    ///
+    /// Returns which cells were reached and the cells without which some of the
+    /// `terminals` couldn't reach `root` anymore, the articulation points between them.
+    fn search(
+        &self,
+        passages: &Array2<Passage>,
+        terminals: &Array2<bool>,
+        root: Coordinates,
+    ) -> (Array2<bool>, Vec<Coordinates>) {
+        // depth first search numbering, 0 where not visited yet
+        let mut discovered: Array2<usize> = Array2::zeros(passages.dim());
+        let mut low: Array2<usize> = Array2::zeros(passages.dim());
+        // terminals in the search tree below each cell
+        let mut below: Array2<usize> = Array2::zeros(passages.dim());
+        let mut cuts: Vec<Coordinates> = Vec::new();
+
+        // cell, next direction to look at and whether the edge to the parent was skipped,
+        // on a periodic grid two directions may lead to the same neighbour
+        let mut stack: Vec<(Coordinates, usize, bool)> = vec![(root, 0, false)];
+        let mut time = 1;
+        discovered[root] = time;
+        low[root] = time;
+        below[root] = usize::from(terminals[root]);
+        while let Some(&(cell, direction, skipped_parent)) = stack.last() {
+            if direction < self.topology.directions() {
+                stack.last_mut().unwrap().1 += 1;
+                let Some(neighbour) = self.topology.neighbour(cell, direction, self.shape) else {
+                    continue;
+                };
+                if passages[neighbour] == Passage::Blocked {
+                    continue;
+                }
+                let parent = stack.len().checked_sub(2).map(|index| stack[index].0);
+                if discovered[neighbour] == 0 {
+                    time += 1;
+                    discovered[neighbour] = time;
+                    low[neighbour] = time;
+                    below[neighbour] = usize::from(terminals[neighbour]);
+                    stack.push((neighbour, 0, false));
+                } else if parent == Some(neighbour) && !skipped_parent {
+                    stack.last_mut().unwrap().2 = true;
+                } else {
+                    low[cell] = low[cell].min(discovered[neighbour]);
+                }
+                continue;
+            }
+
+            stack.pop();
+            let Some(&(parent, ..)) = stack.last() else {
+                break;
+            };
+            low[parent] = low[parent].min(low[cell]);
+            below[parent] += below[cell];
+            // the root is a terminal itself, so everything cut off below `parent` that
+            // holds a terminal is separated from one
+            if parent != root && low[cell] >= discovered[parent] && below[cell] > 0 {
+                cuts.push(parent);
+            }
+        }
+        (discovered.map(|&time| time != 0), cuts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::wfc::{
+        limits::Limits,
+        tileset::fixtures::{self, FLOOR, WALL},
+        SolverRng,
+    };
+
+    #[derive(Default)]
+    struct Contradictions(Vec<Coordinates>);
+
+    impl Observer for Contradictions {
+        fn on_contradiction(&mut self, coords: Coordinates) {
+            self.0.push(coords);
+        }
+    }
+
+    fn only(tile: usize) -> impl Fn(usize) -> bool {
+        move |other| other == tile
+    }
+
+    fn allows(wave_function: &WaveFunction, (x, y): Coordinates, tile: usize) -> bool {
+        wave_function.wave_field[[x, y, tile]]
+    }
+
+    // a 5x3 grid walled off except for the middle row and a niche above its center
+    fn corridor() -> WaveFunction {
+        let mut wave_function = WaveFunction::new((5, 3), fixtures::floors_and_walls());
+        for x in 0..5 {
+            if x != 2 {
+                wave_function.restrict((x, 0), only(WALL)).unwrap();
+            }
+            wave_function.restrict((x, 2), only(WALL)).unwrap();
+        }
+        wave_function
+    }
+
+    #[test]
+    fn cells_a_path_has_to_pass_become_floor() {
+        let mut wave_function = corridor();
+        wave_function.require_path(&[FLOOR], (0, 1), (4, 1));
+        wave_function.apply_constraints(&mut ()).unwrap();
+        for x in 0..5 {
+            assert!(!allows(&wave_function, (x, 1), WALL), "at ({x}, 1)");
+        }
+        // the niche isn't needed for the path
+        assert!(allows(&wave_function, (2, 0), FLOOR));
+        assert!(allows(&wave_function, (2, 0), WALL));
+    }
+
+    #[test]
+    fn cells_cut_off_from_the_floor_become_walls() {
+        let mut wave_function = WaveFunction::new((5, 3), fixtures::floors_and_walls());
+        wave_function.restrict((0, 1), only(FLOOR)).unwrap();
+        for y in 0..3 {
+            wave_function.restrict((2, y), only(WALL)).unwrap();
+        }
+        wave_function.require_connected(&[FLOOR]);
+        wave_function.apply_constraints(&mut ()).unwrap();
+        for (x, y) in ndarray::indices((5, 3)) {
+            let reachable = x < 2;
+            assert_eq!(
+                allows(&wave_function, (x, y), FLOOR),
+                reachable,
+                "at ({x}, {y})"
+            );
+        }
+        assert!(allows(&wave_function, (1, 1), WALL));
+    }
+
+    #[test]
+    fn separated_floors_are_a_contradiction() {
+        let mut wave_function = WaveFunction::new((5, 3), fixtures::floors_and_walls());
+        for x in [0, 4] {
+            wave_function.restrict((x, 1), only(FLOOR)).unwrap();
+        }
+        for y in 0..3 {
+            wave_function.restrict((2, y), only(WALL)).unwrap();
+        }
+        wave_function.require_connected(&[FLOOR]);
+        let mut contradictions = Contradictions::default();
+        let res =
+            wave_function.collapse_with(&mut SolverRng::seed_from_u64(0), &mut contradictions);
+        assert_eq!(res, Err(Contradiction));
+        assert_eq!(contradictions.0, [(4, 1)]);
+    }
+
+    #[test]
+    fn solved_floors_form_one_region() {
+        let mut wave_function = WaveFunction::new((10, 10), fixtures::floors_and_walls());
+        wave_function.require_connected(&[FLOOR]);
+        let limits = Limits {
+            max_attempts: Some(50),
+            ..Limits::default()
+        };
+        let Ok(solved) = wave_function.solve(&mut SolverRng::seed_from_u64(3), limits, &mut ())
+        else {
+            panic!("no connected output within 50 attempts");
+        };
+        let result = solved.get_result().unwrap();
+        let floors = result.iter().filter(|&&tile| tile == FLOOR).count();
+        let start = result
+            .indexed_iter()
+            .find(|&(_, &tile)| tile == FLOOR)
+            .map(|(coords, _)| coords)
+            .expect("some floor");
+
+        let mut reached = Array2::from_elem(result.dim(), false);
+        reached[start] = true;
+        let mut queue = VecDeque::from([start]);
+        let mut region = 0;
+        while let Some(cell) = queue.pop_front() {
+            region += 1;
+            for direction in 0..solved.topology.directions() {
+                if let Some(neighbour) = solved.topology.neighbour(cell, direction, solved.shape) {
+                    if result[neighbour] == FLOOR && !reached[neighbour] {
+                        reached[neighbour] = true;
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+        }
+        assert_eq!(region, floors);
+    }
+}
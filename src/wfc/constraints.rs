@@ -0,0 +1,322 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    connectivity::Connectivity, observer::Observer, symmetry::MirrorRule, topology::Topology,
+    Contradiction, Coordinates, WaveFunction,
+};
+
+/// Rules about the output as a whole that the solver keeps on top of the adjacency
+/// of the tileset. They are carried over to every attempt of a solve and stored in
+/// checkpoints, but aren't part of snapshots.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Constraints {
+    pub(crate) counts: Vec<TileCount>,
+    pub(crate) connectivity: Vec<Connectivity>,
+    pub(crate) symmetry: Vec<MirrorRule>,
+}
+
+impl Constraints {
+    fn is_empty(&self) -> bool {
+        self.counts.is_empty() && self.connectivity.is_empty() && self.symmetry.is_empty()
+    }
+
+    /// Returns `false` if a constraint refers to tiles or cells that don't exist in an
+    /// output of `shape` over `num_tiles` tiles, like ones read from a damaged checkpoint.
+    pub(crate) fn fit(&self, shape: [usize; 2], num_tiles: usize) -> bool {
+        self.counts
+            .iter()
+            .all(|count| count.tiles.iter().all(|&tile| tile < num_tiles) && count.min <= count.max)
+            && self
+                .connectivity
+                .iter()
+                .all(|connectivity| connectivity.fits(shape, num_tiles))
+            && self.symmetry.iter().all(|rule| rule.fits(num_tiles))
+    }
+}
+
+/// How many cells of the output may hold one of `tiles`, from `min` to `max` cells
+/// both included. See [`WaveFunction::add_tile_count`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileCount {
+    pub tiles: Vec<usize>,
+    pub min: usize,
+    pub max: usize,
+}
+
+impl TileCount {
+    pub fn exactly(tiles: impl Into<Vec<usize>>, count: usize) -> Self {
+        Self::between(tiles, count, count)
+    }
+    pub fn at_least(tiles: impl Into<Vec<usize>>, min: usize) -> Self {
+        Self::between(tiles, min, usize::MAX)
+    }
+    pub fn at_most(tiles: impl Into<Vec<usize>>, max: usize) -> Self {
+        Self::between(tiles, 0, max)
+    }
+    pub fn between(tiles: impl Into<Vec<usize>>, min: usize, max: usize) -> Self {
+        TileCount {
+            tiles: tiles.into(),
+            min,
+            max,
+        }
+    }
+}
+
+// where a tile count stands, cells collapsed to a tile of the group and cells that
+// still allow one
+struct Tally {
+    collapsed: usize,
+    possible: usize,
+}
+
+impl<T: Topology> WaveFunction<T> {
+    /// Requires the number of cells holding one of `count.tiles` to stay within its
+    /// bounds.
+    ///
+    /// Collapses are checked against the count from the next one on: once a group
+    /// reaches its maximum it's removed from every other cell, and while it's below its
+    /// minimum some collapses place one of its tiles, spread over the cells left. Once
+    /// only as many cells can hold the group as it still needs, they are reserved for
+    /// it. Running out of cells for a minimum is a [`Contradiction`] like any other,
+    /// so the attempt is retried.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count.tiles` contains ids that aren't in the tileset or `min` is
+    /// above `max`.
+    pub fn add_tile_count(&mut self, count: TileCount) {
+        assert!(
+            count.tiles.iter().all(|&tile| tile < self.tileset.len()),
+            "tile count refers to tiles that aren't in the tileset"
+        );
+        assert!(
+            count.min <= count.max,
+            "tile count minimum is above its maximum"
+        );
+        self.constraints.counts.push(count);
+    }
+    pub fn tile_counts(&self) -> &[TileCount] {
+        &self.constraints.counts
+    }
+
+    /// Removes what the constraints rule out until nothing changes anymore.
+    ///
+    /// Returns the index of a tile count that is still below its minimum, if any.
+    pub(crate) fn apply_constraints<O: Observer + ?Sized>(
+        &mut self,
+        observer: &mut O,
+    ) -> Result<Option<usize>, Contradiction> {
+        if self.constraints.is_empty() {
+            return Ok(None);
+        }
+        loop {
+            let mut changed: Vec<Coordinates> = Vec::new();
+            let mut below_minimum = None;
+            for index in 0..self.constraints.counts.len() {
+                let count = self.constraints.counts[index].clone();
+                let Tally {
+                    collapsed,
+                    possible,
+                } = self.tally(&count.tiles);
+                if collapsed > count.max || possible < count.min {
+                    return Err(self.constraint_contradiction(None, observer));
+                }
+
+                // cells that aren't collapsed yet and still allow the group
+                let open = |wave_function: &Self, (x, y): Coordinates| {
+                    wave_function.entropy_field[[x, y]] > 1
+                        && count
+                            .tiles
+                            .iter()
+                            .any(|&tile| wave_function.wave_field[[x, y, tile]])
+                };
+                let remove: Vec<usize> = if collapsed == count.max && possible > collapsed {
+                    // the group is full, no other cell may take it
+                    count.tiles.clone()
+                } else if possible == count.min && collapsed < count.min {
+                    // every cell that can hold the group is needed for it
+                    (0..self.tileset.len())
+                        .filter(|tile| !count.tiles.contains(tile))
+                        .collect()
+                } else {
+                    if collapsed < count.min {
+                        below_minimum.get_or_insert(index);
+                    }
+                    continue;
+                };
+                let [shape_x, shape_y] = self.shape;
+                for coords in (0..shape_x).flat_map(|x| (0..shape_y).map(move |y| (x, y))) {
+                    if !open(self, coords) {
+                        continue;
+                    }
+                    self.ban_where(coords, |tile| remove.contains(&tile), observer);
+                    changed.push(coords);
+                }
+            }
+            for index in 0..self.constraints.symmetry.len() {
+                let rule = self.constraints.symmetry[index].clone();
+                changed.extend(self.apply_symmetry(&rule, observer));
+            }
+            for index in 0..self.constraints.connectivity.len() {
+                let connectivity = self.constraints.connectivity[index].clone();
+                changed.extend(self.apply_connectivity(&connectivity, observer)?);
+            }
+            if changed.is_empty() {
+                return Ok(below_minimum);
+            }
+            self.propagate(changed, observer)?;
+        }
+    }
+
+    /// Counts a contradiction a constraint ran into and reports it at `coords`, or at
+    /// the cell collapsed last if the constraint has no cell to blame.
+    pub(crate) fn constraint_contradiction<O: Observer + ?Sized>(
+        &mut self,
+        coords: Option<Coordinates>,
+        observer: &mut O,
+    ) -> Contradiction {
+        let coords = coords
+            .or_else(|| self.history.last().map(|&(coords, _)| coords))
+            .unwrap_or((0, 0));
+        self.stats.contradictions += 1;
+        observer.on_contradiction(coords);
+        Contradiction
+    }
+
+    /// Picks a cell for tile count `index` to grow into, the lowest entropy one of
+    /// those that aren't collapsed and can still hold the group, together with the
+    /// group's tiles it allows.
+    ///
+    /// Steers with the chance of the tiles the group still misses among the cells that
+    /// aren't collapsed, so they are spread over the rest of the solve instead of
+    /// clustering in the next collapses. Returns `None` to collapse as usual.
+    pub(crate) fn steer_to_minimum<R: Rng + ?Sized>(
+        &self,
+        index: usize,
+        rng: &mut R,
+    ) -> Option<(Coordinates, Vec<usize>)> {
+        let count = &self.constraints.counts[index];
+        let missing = count.min.saturating_sub(self.tally(&count.tiles).collapsed);
+        let open = self
+            .entropy_field
+            .iter()
+            .filter(|&&entropy| entropy > 1)
+            .count();
+        if rng.gen_range(0..open.max(1)) >= missing {
+            return None;
+        }
+        let tiles = &count.tiles;
+        let candidates: Vec<(Coordinates, usize)> = self
+            .entropy_field
+            .indexed_iter()
+            .filter(|&((x, y), &entropy)| {
+                entropy > 1 && tiles.iter().any(|&tile| self.wave_field[[x, y, tile]])
+            })
+            .map(|(coords, &entropy)| (coords, entropy))
+            .collect();
+        let min = candidates.iter().map(|&(_, entropy)| entropy).min()?;
+        let lowest: Vec<Coordinates> = candidates
+            .into_iter()
+            .filter(|&(_, entropy)| entropy == min)
+            .map(|(coords, _)| coords)
+            .collect();
+        let &(x, y) = lowest.choose(rng)?;
+        let options = tiles
+            .iter()
+            .copied()
+            .filter(|&tile| self.wave_field[[x, y, tile]])
+            .collect();
+        Some(((x, y), options))
+    }
+
+    fn tally(&self, tiles: &[usize]) -> Tally {
+        let mut tally = Tally {
+            collapsed: 0,
+            possible: 0,
+        };
+        for ((x, y), &entropy) in self.entropy_field.indexed_iter() {
+            if tiles.iter().any(|&tile| self.wave_field[[x, y, tile]]) {
+                tally.possible += 1;
+                if entropy == 1 {
+                    tally.collapsed += 1;
+                }
+            }
+        }
+        tally
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::wfc::{
+        limits::{GiveUpReason, Limits},
+        tileset::fixtures,
+        SolverRng,
+    };
+
+    const SHAPE: (usize, usize) = (12, 12);
+
+    #[derive(Default)]
+    struct Contradictions(Vec<Coordinates>);
+
+    impl Observer for Contradictions {
+        fn on_contradiction(&mut self, coords: Coordinates) {
+            self.0.push(coords);
+        }
+    }
+
+    fn limits(max_attempts: usize) -> Limits {
+        Limits {
+            max_attempts: Some(max_attempts),
+            ..Limits::default()
+        }
+    }
+
+    fn count(result: &ndarray::Array2<usize>, tile: usize) -> usize {
+        result.iter().filter(|&&id| id == tile).count()
+    }
+
+    #[test]
+    fn failed_counts_are_reported_as_contradictions() {
+        let mut wave_function = WaveFunction::new(SHAPE, fixtures::tileset());
+        // far more than the adjacency allows, only found out while solving
+        wave_function.add_tile_count(TileCount::at_least([1], 100));
+        let mut contradictions = Contradictions::default();
+        let Err(gave_up) = wave_function.solve(
+            &mut SolverRng::seed_from_u64(0),
+            limits(4),
+            &mut contradictions,
+        ) else {
+            panic!("a minimum of 100 cells can't be met");
+        };
+        assert_eq!(gave_up.reason, GiveUpReason::MaxAttempts(4));
+        assert_eq!(contradictions.0.len(), 4);
+    }
+
+    #[test]
+    fn exact_counts_are_met() {
+        let mut wave_function = WaveFunction::new(SHAPE, fixtures::tileset());
+        wave_function.add_tile_count(TileCount::exactly([0], 30));
+        let solved = wave_function
+            .solve(&mut SolverRng::seed_from_u64(0), limits(200), &mut ())
+            .unwrap();
+        assert_eq!(count(&solved.get_result().unwrap(), 0), 30);
+    }
+
+    #[test]
+    fn minimums_are_spread_over_the_solve() {
+        let mut wave_function = WaveFunction::new(SHAPE, fixtures::tileset());
+        wave_function.add_tile_count(TileCount::at_least([3], 20));
+        let solved = wave_function
+            .solve(&mut SolverRng::seed_from_u64(0), limits(200), &mut ())
+            .unwrap();
+        assert!(count(&solved.get_result().unwrap(), 3) >= 20);
+        let first = &solved.history()[..20];
+        assert!(first.iter().filter(|&&(_, tile)| tile == 3).count() < 20);
+    }
+}
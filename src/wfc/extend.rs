@@ -0,0 +1,166 @@
+use std::{fs, sync::Arc};
+
+use ndarray::Array2;
+
+use super::{
+    tileset::{Tileset, TilesetError},
+    Contradiction, WaveFunction,
+};
+
+/// Number of cells to add on each side of an output, see [`WaveFunction::extend`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Margins {
+    pub left: usize,
+    pub right: usize,
+    pub up: usize,
+    pub down: usize,
+}
+
+impl Margins {
+    /// The same number of cells on every side.
+    pub fn all(cells: usize) -> Self {
+        Margins {
+            left: cells,
+            right: cells,
+            up: cells,
+            down: cells,
+        }
+    }
+}
+
+impl WaveFunction {
+    /// Creates a wave function for the finished output `tiles` grown by `margins`.
+    ///
+    /// The cells of `tiles` start out collapsed to their tile and stay that way, only
+    /// the margin around them is left to solve. Returns [`Contradiction`] if no margin
+    /// fits around them, for example when a tile on their edge was only ever seen at the
+    /// border of the sample.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tiles` contains ids that aren't in `tileset`.
+    pub fn extend(
+        tiles: &Array2<usize>,
+        tileset: impl Into<Arc<Tileset>>,
+        margins: Margins,
+    ) -> Result<Self, Contradiction> {
+        let tileset = tileset.into();
+        assert!(
+            tiles.iter().all(|&id| id < tileset.len()),
+            "output contains tiles that aren't in the tileset"
+        );
+        let (width, height) = tiles.dim();
+        let shape = (
+            margins.left + width + margins.right,
+            margins.up + height + margins.down,
+        );
+        let mut wave_function = WaveFunction::new(shape, tileset);
+        for ((x, y), &id) in tiles.indexed_iter() {
+            wave_function.restrict((margins.left + x, margins.up + y), |tile| tile == id)?;
+        }
+        Ok(wave_function)
+    }
+
+    /// Writes the tile ids of the finished output to `path` as JSON, one array per row.
+    /// Read them back with [`load_tile_ids`].
+    pub fn save_tile_ids(&self, path: &str) -> Result<(), TilesetError> {
+        let Some(tiles) = self.get_result() else {
+            return Err(TilesetError::Invalid(
+                "only finished outputs can be saved as tile ids".to_string(),
+            ));
+        };
+        let rows: Vec<Vec<usize>> = tiles
+            .columns()
+            .into_iter()
+            .map(|row| row.to_vec())
+            .collect();
+        fs::write(
+            path,
+            serde_json::to_string(&rows).expect("tile ids are always representable as json"),
+        )?;
+        Ok(())
+    }
+}
+
+/// Reads tile ids written by [`WaveFunction::save_tile_ids`], indexed `[x, y]`.
+pub fn load_tile_ids(path: &str) -> Result<Array2<usize>, TilesetError> {
+    let rows: Vec<Vec<usize>> = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let width = rows.first().map_or(0, Vec::len);
+    if width == 0 || rows.iter().any(|row| row.len() != width) {
+        return Err(TilesetError::Invalid(
+            "tile ids must be non empty rows of the same length".to_string(),
+        ));
+    }
+    Ok(Array2::from_shape_fn((width, rows.len()), |(x, y)| {
+        rows[y][x]
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::wfc::{limits::Limits, tileset::fixtures, SolverRng};
+
+    fn solve(wave_function: WaveFunction, seed: u64) -> Option<WaveFunction> {
+        let limits = Limits {
+            max_attempts: Some(100),
+            ..Limits::default()
+        };
+        let rng = &mut SolverRng::seed_from_u64(seed);
+        wave_function.solve(rng, limits, &mut ()).ok()
+    }
+
+    fn solved(shape: (usize, usize), seed: u64) -> WaveFunction {
+        solve(WaveFunction::new(shape, fixtures::tileset()), seed).unwrap()
+    }
+
+    #[test]
+    fn tile_ids_round_trip_row_by_row() {
+        let path = env::temp_dir()
+            .join(format!("tiling-wfc-{}-ids.json", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let wave_function = solved((6, 4), 0);
+        wave_function.save_tile_ids(&path).unwrap();
+        let rows: Vec<Vec<usize>> =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let loaded = load_tile_ids(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let tiles = wave_function.get_result().unwrap();
+        assert_eq!(loaded, tiles);
+        assert_eq!((rows[0].len(), rows.len()), (6, 4));
+        for ((x, y), &id) in tiles.indexed_iter() {
+            assert_eq!(rows[y][x], id);
+        }
+    }
+
+    #[test]
+    fn extended_outputs_keep_the_original_tiles() {
+        let margins = Margins {
+            left: 2,
+            right: 1,
+            up: 3,
+            down: 0,
+        };
+        let tileset = Arc::new(fixtures::tileset());
+        // tiles seen only on the border of the sample can't be surrounded by a margin
+        let (tiles, extended) = (0..)
+            .find_map(|seed| {
+                let tiles = solved((5, 4), seed).get_result().unwrap();
+                let extended = WaveFunction::extend(&tiles, tileset.clone(), margins).ok()?;
+                let extended = solve(extended, seed)?;
+                Some((tiles, extended.get_result().unwrap()))
+            })
+            .unwrap();
+
+        assert_eq!(extended.dim(), (2 + 5 + 1, 3 + 4));
+        for ((x, y), &id) in tiles.indexed_iter() {
+            assert_eq!(extended[[margins.left + x, margins.up + y]], id);
+        }
+    }
+}
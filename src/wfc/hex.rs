@@ -0,0 +1,283 @@
+use std::fmt;
+
+use image::{ImageResult, Rgb, RgbImage};
+
+use super::{tileset::TILE_SIZE, topology::Topology, Coordinates, WaveFunction};
+
+// same gray the square render uses for cells that aren't collapsed yet
+const UNCOLLAPSED: Rgb<u8> = Rgb([130, 130, 130]);
+// around and between the hexes of the map
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// Side of a pointy topped hex cell, the rows of a hex map run east to west.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HexDirection {
+    East,
+    West,
+    NorthEast,
+    SouthWest,
+    NorthWest,
+    SouthEast,
+}
+impl HexDirection {
+    /// All directions, direction `i` of the [`Hex`] topology is `HexDirection::ALL[i]`.
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::East,
+        HexDirection::West,
+        HexDirection::NorthEast,
+        HexDirection::SouthWest,
+        HexDirection::NorthWest,
+        HexDirection::SouthEast,
+    ];
+
+    pub fn opposite(self) -> HexDirection {
+        HexDirection::ALL[self.index() ^ 1]
+    }
+
+    /// Returns the number of the direction in the [`Hex`] topology.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the `(dq, dr)` step to the neighbour in axial coordinates.
+    pub fn axial_step(self) -> (isize, isize) {
+        match self {
+            HexDirection::East => (1, 0),
+            HexDirection::West => (-1, 0),
+            HexDirection::NorthEast => (1, -1),
+            HexDirection::SouthWest => (-1, 1),
+            HexDirection::NorthWest => (0, -1),
+            HexDirection::SouthEast => (0, 1),
+        }
+    }
+}
+impl fmt::Display for HexDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HexDirection::East => "east",
+            HexDirection::West => "west",
+            HexDirection::NorthEast => "north east",
+            HexDirection::SouthWest => "south west",
+            HexDirection::NorthWest => "north west",
+            HexDirection::SouthEast => "south east",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// How the cells of a hex map are stored in the `[x, y]` grid of a [`WaveFunction`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HexLayout {
+    /// `x` is the column and `y` the row, odd rows are shifted half a cell to the
+    /// east, so the map is a rectangle.
+    Offset,
+    /// `x` is `q` and `y` is `r` of axial coordinates, every row starts half a cell
+    /// further east than the one above, so the map is a parallelogram.
+    Axial,
+}
+
+/// Grid of pointy topped hexagons where every cell has six neighbours, numbered as in
+/// [`HexDirection::ALL`].
+///
+/// Both layouts have the same directions, so a tileset learned with one of them can
+/// be solved with the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hex {
+    pub layout: HexLayout,
+}
+
+impl Hex {
+    pub fn offset() -> Self {
+        Hex {
+            layout: HexLayout::Offset,
+        }
+    }
+    pub fn axial() -> Self {
+        Hex {
+            layout: HexLayout::Axial,
+        }
+    }
+
+    /// Returns the axial `(q, r)` of the cell stored at `(x, y)`.
+    pub fn to_axial(&self, (x, y): Coordinates) -> (isize, isize) {
+        let (x, y) = (x as isize, y as isize);
+        match self.layout {
+            HexLayout::Offset => (x - y.div_euclid(2), y),
+            HexLayout::Axial => (x, y),
+        }
+    }
+
+    /// Returns where the cell at axial `(q, r)` is stored in a grid of `shape`, or
+    /// `None` if it's outside of it.
+    pub fn from_axial(
+        &self,
+        (q, r): (isize, isize),
+        [width, height]: [usize; 2],
+    ) -> Option<Coordinates> {
+        let (x, y) = match self.layout {
+            HexLayout::Offset => (q + r.div_euclid(2), r),
+            HexLayout::Axial => (q, r),
+        };
+        let inside = x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height;
+        inside.then_some((x as usize, y as usize))
+    }
+}
+
+impl Topology for Hex {
+    fn directions(&self) -> usize {
+        HexDirection::ALL.len()
+    }
+    fn neighbour(
+        &self,
+        coords: Coordinates,
+        direction: usize,
+        shape: [usize; 2],
+    ) -> Option<Coordinates> {
+        let (q, r) = self.to_axial(coords);
+        let (dq, dr) = HexDirection::ALL[direction].axial_step();
+        self.from_axial((q + dq, r + dr), shape)
+    }
+    fn opposite(&self, direction: usize) -> usize {
+        HexDirection::ALL[direction].opposite().index()
+    }
+    fn name(&self) -> &'static str {
+        match self.layout {
+            HexLayout::Offset => "hex offset",
+            HexLayout::Axial => "hex axial",
+        }
+    }
+}
+
+impl WaveFunction<Hex> {
+    /// Renders the output as hexagons `width` pixels wide, every tile image is stretched
+    /// over its hexagon and cut to its shape. Cells that aren't collapsed yet are gray.
+    pub fn render_hex(&self, width: u32) -> RgbImage {
+        let [shape_x, shape_y] = self.shape;
+        // pointy topped hexagons, `size` is the distance from the center to a corner
+        let hex_width = f64::from(width.max(1));
+        let size = hex_width / 3f64.sqrt();
+        let center = |(q, r): (isize, isize)| {
+            (
+                hex_width * (q as f64 + r as f64 / 2.0) + hex_width / 2.0,
+                1.5 * size * r as f64 + size,
+            )
+        };
+
+        let (mut image_width, mut image_height) = (0.0f64, 0.0f64);
+        for x in 0..shape_x {
+            for y in 0..shape_y {
+                let (center_x, center_y) = center(self.topology.to_axial((x, y)));
+                image_width = image_width.max(center_x + hex_width / 2.0);
+                image_height = image_height.max(center_y + size);
+            }
+        }
+
+        RgbImage::from_fn(
+            image_width.ceil() as u32,
+            image_height.ceil() as u32,
+            |px, py| {
+                let (px, py) = (f64::from(px) + 0.5, f64::from(py) + 0.5);
+                // hex of the pixel, relative to the center of the hex at `(0, 0)`
+                let (rel_x, rel_y) = (px - hex_width / 2.0, py - size);
+                let q = (3f64.sqrt() / 3.0 * rel_x - rel_y / 3.0) / size;
+                let r = 2.0 / 3.0 * rel_y / size;
+                let axial = round_axial(q, r);
+                let Some(coords) = self.topology.from_axial(axial, self.shape) else {
+                    return BACKGROUND;
+                };
+                let Some(tile_id) = self.get_tile_id(coords) else {
+                    return UNCOLLAPSED;
+                };
+
+                // position inside the bounding box of the hexagon, from 0 to 1
+                let (center_x, center_y) = center(axial);
+                let u = (px - center_x) / hex_width + 0.5;
+                let v = (py - center_y) / (2.0 * size) + 0.5;
+                let pixel = |t: f64| ((t * TILE_SIZE as f64) as usize).min(TILE_SIZE - 1);
+                let (r, g, b) = self.tileset[tile_id].get_image()[[pixel(u), pixel(v)]].get();
+                Rgb([r, g, b])
+            },
+        )
+    }
+
+    /// Writes [`WaveFunction::render_hex`] to `path`, the format follows the extension.
+    pub fn save_hex_image(&self, path: &str, width: u32) -> ImageResult<()> {
+        self.render_hex(width).save(path)
+    }
+}
+
+// rounds fractional axial coordinates to the hex that contains them
+fn round_axial(q: f64, r: f64) -> (isize, isize) {
+    let s = -q - r;
+    let (mut round_q, mut round_r, round_s) = (q.round(), r.round(), s.round());
+    let (diff_q, diff_r, diff_s) = (
+        (round_q - q).abs(),
+        (round_r - r).abs(),
+        (round_s - s).abs(),
+    );
+    if diff_q > diff_r && diff_q > diff_s {
+        round_q = -round_r - round_s;
+    } else if diff_r > diff_s {
+        round_r = -round_q - round_s;
+    }
+    (round_q as isize, round_r as isize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAPE: [usize; 2] = [5, 6];
+
+    fn cells() -> impl Iterator<Item = Coordinates> {
+        ndarray::indices((SHAPE[0], SHAPE[1])).into_iter()
+    }
+
+    #[test]
+    fn offset_rows_shift_every_other_row() {
+        let hex = Hex::offset();
+        assert_eq!(hex.to_axial((0, 0)), (0, 0));
+        assert_eq!(hex.to_axial((2, 1)), (2, 1));
+        assert_eq!(hex.to_axial((2, 2)), (1, 2));
+        assert_eq!(hex.to_axial((0, 5)), (-2, 5));
+        assert_eq!(hex.from_axial((-2, 5), SHAPE), Some((0, 5)));
+        assert_eq!(hex.from_axial((-1, 2), SHAPE), Some((0, 2)));
+        assert_eq!(hex.from_axial((-2, 2), SHAPE), None);
+        assert_eq!(Hex::axial().to_axial((2, 2)), (2, 2));
+        assert_eq!(Hex::axial().from_axial((-1, 2), SHAPE), None);
+    }
+
+    #[test]
+    fn axial_coordinates_lead_back_to_the_cell() {
+        for hex in [Hex::offset(), Hex::axial()] {
+            for cell in cells() {
+                assert_eq!(hex.from_axial(hex.to_axial(cell), SHAPE), Some(cell));
+            }
+        }
+    }
+
+    #[test]
+    fn neighbours_are_mutual() {
+        for hex in [Hex::offset(), Hex::axial()] {
+            for cell in cells() {
+                for direction in 0..hex.directions() {
+                    if let Some(neighbour) = hex.neighbour(cell, direction, SHAPE) {
+                        let back = hex.opposite(direction);
+                        assert_eq!(hex.neighbour(neighbour, back, SHAPE), Some(cell));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn inner_offset_cells_have_six_neighbours() {
+        let hex = Hex::offset();
+        for cell in [(2, 2), (2, 3)] {
+            let neighbours = (0..hex.directions())
+                .filter_map(|direction| hex.neighbour(cell, direction, SHAPE))
+                .count();
+            assert_eq!(neighbours, 6, "at {cell:?}");
+        }
+    }
+}
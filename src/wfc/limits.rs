@@ -0,0 +1,402 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    observer::Observer,
+    snapshot::Snapshot,
+    stats::SolveStats,
+    topology::{Square, Topology},
+    WaveFunction,
+};
+
+/// Shared flag to stop a solve from another thread, clones refer to the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Asks every solve holding this token to give up after its current collapse.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// When to stop retrying, everything unlimited by default.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    pub max_attempts: Option<usize>,
+    /// Wall time for all attempts together.
+    pub timeout: Option<Duration>,
+    pub cancel: Option<CancelToken>,
+}
+
+/// How far a retry loop had come, see [`Attempts::progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    /// Number of the current attempt, starting at 1.
+    pub attempt: usize,
+    /// Time spent on all attempts so far, counted against [`Limits::timeout`].
+    pub elapsed: Duration,
+}
+
+/// Why a solve gave up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GiveUpReason {
+    MaxAttempts(usize),
+    Timeout(Duration),
+    Cancelled,
+    /// The state every attempt starts from already contradicts itself or the
+    /// constraints, so no attempt can succeed.
+    Unsatisfiable,
+}
+
+impl fmt::Display for GiveUpReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GiveUpReason::MaxAttempts(max) => {
+                write!(f, "limit of {max} attempts reached")
+            }
+            GiveUpReason::Timeout(timeout) => write!(f, "time limit of {timeout:?} reached"),
+            GiveUpReason::Cancelled => write!(f, "cancelled"),
+            GiveUpReason::Unsatisfiable => write!(f, "the constraints can't be met"),
+        }
+    }
+}
+
+/// Error returned when a solve hit one of its [`Limits`].
+pub struct GaveUp<T: Topology = Square> {
+    pub reason: GiveUpReason,
+    pub attempts: usize,
+    /// The attempt that collapsed the most cells, without contradictions. An attempt
+    /// that failed is taken back to the state before its last collapse.
+    pub best: Box<WaveFunction<T>>,
+}
+
+impl<T: Topology> fmt::Debug for GaveUp<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GaveUp")
+            .field("reason", &self.reason)
+            .field("attempts", &self.attempts)
+            .field("best_collapsed_cells", &self.best.collapsed_cells())
+            .finish()
+    }
+}
+
+impl<T: Topology> fmt::Display for GaveUp<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.reason == GiveUpReason::Unsatisfiable {
+            return write!(f, "gave up, {}", self.reason);
+        }
+        let [shape_x, shape_y] = self.best.shape();
+        write!(
+            f,
+            "gave up after {} attempts ({}), the best attempt collapsed {} of {} cells",
+            self.attempts,
+            self.reason,
+            self.best.collapsed_cells(),
+            shape_x * shape_y
+        )
+    }
+}
+
+impl<T: Topology> std::error::Error for GaveUp<T> {}
+
+/// Bookkeeping of a retry loop under [`Limits`], for runners that drive the
+/// collapses themselves. [`WaveFunction::solve`] is the plain version.
+pub struct Attempts<T: Topology = Square> {
+    limits: Limits,
+    started: Instant,
+    count: usize,
+    best: Option<WaveFunction<T>>,
+    // state every attempt starts from, a blank wave function if `None`
+    start: Option<Snapshot>,
+    // summed over the attempts that ended
+    other_stats: SolveStats,
+}
+
+impl<T: Topology + Clone> Attempts<T> {
+    /// Starts the clock of `limits.timeout`.
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            started: Instant::now(),
+            count: 1,
+            best: None,
+            start: None,
+            other_stats: SolveStats::default(),
+        }
+    }
+    /// Like [`Attempts::new`], starting every new attempt from the state `start` is in,
+    /// with the cells it already restricted or collapsed.
+    pub fn starting_from(limits: Limits, start: &WaveFunction<T>) -> Self {
+        Self {
+            start: Some(start.snapshot()),
+            ..Self::new(limits)
+        }
+    }
+    /// Continues the attempt count and the clock of `progress` for a solve resumed from
+    /// a checkpoint, starting every new attempt from `start` like the interrupted run
+    /// did, see [`Resumed`](super::checkpoint::Resumed).
+    pub fn resuming(limits: Limits, start: Option<Snapshot>, progress: Progress) -> Self {
+        let started = Instant::now()
+            .checked_sub(progress.elapsed)
+            .unwrap_or_else(Instant::now);
+        Self {
+            started,
+            count: progress.attempt.max(1),
+            start,
+            ..Self::new(limits)
+        }
+    }
+    /// Returns a wave function over the tileset, topology and constraints of
+    /// `wave_function` in the state every attempt starts from.
+    pub fn restart(&self, wave_function: &WaveFunction<T>) -> WaveFunction<T> {
+        wave_function.fresh_attempt(self.start.as_ref())
+    }
+    /// Ends the current attempt, which ran into a contradiction or was `interrupted`.
+    ///
+    /// Returns a fresh wave function for the next attempt, or gives up with the best
+    /// attempt so far if `interrupted` is set or no attempts are left. An attempt that
+    /// ran into a contradiction before collapsing anything fails the same way every
+    /// time, so that gives up as [`GiveUpReason::Unsatisfiable`].
+    pub fn next(
+        &mut self,
+        mut wave_function: WaveFunction<T>,
+        interrupted: Option<GiveUpReason>,
+    ) -> Result<WaveFunction<T>, GaveUp<T>> {
+        let collapsed =
+            wave_function.history.len() > self.start.as_ref().map_or(0, Snapshot::collapses);
+        let unsatisfiable = interrupted.is_none() && !collapsed;
+        if interrupted.is_none() && collapsed {
+            wave_function.undo();
+        }
+        let reason = interrupted
+            .or(unsatisfiable.then_some(GiveUpReason::Unsatisfiable))
+            .or(match self.limits.max_attempts {
+                Some(max) if self.count >= max => Some(GiveUpReason::MaxAttempts(max)),
+                _ => None,
+            });
+        let next = reason.is_none().then(|| self.restart(&wave_function));
+
+        self.other_stats += wave_function.stats();
+        let is_best = match &self.best {
+            Some(best) => wave_function.collapsed_cells() > best.collapsed_cells(),
+            None => true,
+        };
+        if is_best {
+            self.best = Some(wave_function);
+        }
+        if let Some(reason) = reason {
+            return Err(GaveUp {
+                reason,
+                attempts: self.count,
+                best: Box::new(self.best.take().unwrap()),
+            });
+        }
+        self.count += 1;
+        Ok(next.unwrap())
+    }
+
+    /// Runs attempts until one of them succeeds or the limits are hit, like
+    /// [`WaveFunction::solve`], starting with `wave_function` and continuing the
+    /// attempt count. Afterwards [`Attempts::count`] is the number of the attempt that
+    /// succeeded.
+    pub fn solve<R: Rng + ?Sized, O: Observer + ?Sized>(
+        &mut self,
+        wave_function: WaveFunction<T>,
+        rng: &mut R,
+        observer: &mut O,
+    ) -> Result<WaveFunction<T>, GaveUp<T>> {
+        self.solve_with(wave_function, rng, observer, |_, _, _| ())
+    }
+
+    // `after_collapse` is called after every successful collapse
+    pub(crate) fn solve_with<R: Rng + ?Sized, O: Observer + ?Sized>(
+        &mut self,
+        mut wave_function: WaveFunction<T>,
+        rng: &mut R,
+        observer: &mut O,
+        mut after_collapse: impl FnMut(&WaveFunction<T>, &R, &Self),
+    ) -> Result<WaveFunction<T>, GaveUp<T>> {
+        loop {
+            observer.on_attempt(self.count);
+            let mut interrupted = None;
+            while !wave_function.done() {
+                interrupted = self.interrupted();
+                if interrupted.is_some() || wave_function.collapse_with(rng, observer).is_err() {
+                    break;
+                }
+                after_collapse(&wave_function, rng, self);
+            }
+            if wave_function.done() {
+                return Ok(wave_function);
+            }
+            wave_function = self.next(wave_function, interrupted)?;
+        }
+    }
+}
+
+impl<T: Topology> Attempts<T> {
+    /// Number of the current attempt, starting at 1.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+    /// Returns the attempt count and time so far, to continue them with
+    /// [`Attempts::resuming`].
+    pub fn progress(&self) -> Progress {
+        Progress {
+            attempt: self.count,
+            elapsed: self.started.elapsed(),
+        }
+    }
+    /// Work of the attempts that ended without success so far, the work of a
+    /// successful one is in its [`WaveFunction::stats`].
+    pub fn other_stats(&self) -> SolveStats {
+        self.other_stats
+    }
+    /// State every attempt starts from, `None` for a blank wave function.
+    pub(crate) fn start(&self) -> Option<&Snapshot> {
+        self.start.as_ref()
+    }
+    /// Returns why to stop right away, check it between collapses.
+    pub fn interrupted(&self) -> Option<GiveUpReason> {
+        if self
+            .limits
+            .cancel
+            .as_ref()
+            .is_some_and(CancelToken::is_cancelled)
+        {
+            return Some(GiveUpReason::Cancelled);
+        }
+        match self.limits.timeout {
+            Some(timeout) if self.started.elapsed() >= timeout => {
+                Some(GiveUpReason::Timeout(timeout))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<T: Topology + Clone> WaveFunction<T> {
+    /// Creates a wave function over the same tileset, topology and constraints for the
+    /// next attempt, in the state `start` captured or blank without one.
+    pub(crate) fn fresh_attempt(&self, start: Option<&Snapshot>) -> WaveFunction<T> {
+        let [shape_x, shape_y] = self.shape;
+        let mut fresh = WaveFunction::with_topology(
+            (shape_x, shape_y),
+            self.tileset.clone(),
+            self.topology.clone(),
+        );
+        if let Some(start) = start {
+            fresh.restore(start);
+        }
+        fresh.constraints.clone_from(&self.constraints);
+        fresh
+    }
+
+    /// Runs attempts on fresh copies of this wave function until one of them succeeds
+    /// or `limits` are hit, reporting every step to `observer`.
+    ///
+    /// Every attempt starts from the state the wave function is in, so restrictions
+    /// made with [`WaveFunction::restrict`] hold in all of them.
+    pub fn solve<R: Rng + ?Sized, O: Observer + ?Sized>(
+        self,
+        rng: &mut R,
+        limits: Limits,
+        observer: &mut O,
+    ) -> Result<WaveFunction<T>, GaveUp<T>> {
+        Attempts::starting_from(limits, &self).solve(self, rng, observer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::wfc::{constraints::TileCount, tileset::fixtures, SolverRng};
+
+    const SHAPE: (usize, usize) = (8, 8);
+
+    fn solve(
+        wave_function: WaveFunction,
+        seed: u64,
+        limits: Limits,
+    ) -> Result<WaveFunction, GaveUp> {
+        wave_function.solve(&mut SolverRng::seed_from_u64(seed), limits, &mut ())
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        // an exact count makes many attempts fail
+        let mut template = WaveFunction::new(SHAPE, fixtures::tileset());
+        template.add_tile_count(TileCount::exactly([0], 20));
+        let limits = Limits {
+            max_attempts: Some(2),
+            ..Limits::default()
+        };
+        let gave_up = (0..)
+            .find_map(|seed| solve(template.fresh_attempt(None), seed, limits.clone()).err())
+            .unwrap();
+        assert_eq!(gave_up.reason, GiveUpReason::MaxAttempts(2));
+        assert_eq!(gave_up.attempts, 2);
+    }
+
+    #[test]
+    fn gives_up_when_the_time_is_up() {
+        let limits = Limits {
+            timeout: Some(Duration::ZERO),
+            ..Limits::default()
+        };
+        let Err(gave_up) = solve(WaveFunction::new(SHAPE, fixtures::tileset()), 0, limits) else {
+            panic!("solved without any time");
+        };
+        assert_eq!(gave_up.reason, GiveUpReason::Timeout(Duration::ZERO));
+        assert_eq!(gave_up.attempts, 1);
+        assert!(gave_up.best.history().is_empty());
+    }
+
+    #[test]
+    fn gives_up_when_cancelled() {
+        let cancel = CancelToken::new();
+        let limits = Limits {
+            cancel: Some(cancel.clone()),
+            ..Limits::default()
+        };
+        cancel.cancel();
+        let Err(gave_up) = solve(WaveFunction::new(SHAPE, fixtures::tileset()), 0, limits) else {
+            panic!("solved after being cancelled");
+        };
+        assert_eq!(gave_up.reason, GiveUpReason::Cancelled);
+    }
+
+    #[test]
+    fn contradicted_start_is_unsatisfiable_without_attempt_limit() {
+        let mut too_many = WaveFunction::new(SHAPE, fixtures::tileset());
+        too_many.add_tile_count(TileCount::at_least([0], SHAPE.0 * SHAPE.1 + 1));
+        let mut empty_cell = WaveFunction::new(SHAPE, fixtures::tileset());
+        assert!(empty_cell.restrict((3, 3), |_| false).is_err());
+
+        for start in [too_many, empty_cell] {
+            let Err(gave_up) = solve(start, 0, Limits::default()) else {
+                panic!("solved a contradicted start");
+            };
+            assert_eq!(gave_up.reason, GiveUpReason::Unsatisfiable);
+            assert_eq!(gave_up.attempts, 1);
+        }
+    }
+}
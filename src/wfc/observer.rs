@@ -0,0 +1,34 @@
+use super::Coordinates;
+
+/// Receives the steps of the solver, attach one with [`WaveFunction::collapse_with`]
+/// or [`WaveFunction::run_with`].
+///
+/// Every method does nothing by default, implement only the ones you need. `()` is
+/// the observer that ignores everything.
+///
+/// [`WaveFunction::collapse_with`]: super::WaveFunction::collapse_with
+/// [`WaveFunction::run_with`]: super::WaveFunction::run_with
+#[allow(unused_variables)]
+pub trait Observer {
+    /// The cell at `coords` has the lowest entropy and will be collapsed next.
+    fn on_select(&mut self, coords: Coordinates) {}
+    /// The cell at `coords` was collapsed to `tile`.
+    fn on_collapse(&mut self, coords: Coordinates, tile: usize) {}
+    /// `tile` is no longer possible in the cell at `coords`.
+    fn on_ban(&mut self, coords: Coordinates, tile: usize) {}
+    /// The cell at `coords` has `entropy` possible tiles left, reported once after
+    /// all tiles removed from it in one go.
+    fn on_entropy_change(&mut self, coords: Coordinates, entropy: usize) {}
+    /// The cell at `coords` has no possible tile left, the current attempt failed.
+    ///
+    /// Also reported when a constraint on the whole output can't be met anymore, at
+    /// the cell it blames or the cell collapsed last.
+    fn on_contradiction(&mut self, coords: Coordinates) {}
+    /// Every cell is collapsed.
+    fn on_complete(&mut self) {}
+    /// Attempt number `attempt`, counting from 1, starts. Only reported by solves that
+    /// retry, like [`Attempts::solve`](super::limits::Attempts::solve).
+    fn on_attempt(&mut self, attempt: usize) {}
+}
+
+impl Observer for () {}
@@ -0,0 +1,264 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use rand::{Rng, SeedableRng};
+
+use super::{
+    limits::{CancelToken, GaveUp, GiveUpReason, Limits},
+    stats::SolveStats,
+    topology::{Square, Topology},
+    SolverRng, WaveFunction,
+};
+
+/// Result of [`WaveFunction::solve_parallel`].
+pub struct Solved<T: Topology = Square> {
+    pub wave_function: WaveFunction<T>,
+    /// Index of the successful attempt, counting from 0.
+    pub attempt: usize,
+    /// Number of attempts that were started, including abandoned ones.
+    pub attempts: usize,
+    /// Seed of the successful attempt, see [`attempt_seed`].
+    pub seed: u64,
+    /// Work done by the attempts that failed or were abandoned, the work of the
+    /// successful one is in [`WaveFunction::stats`].
+    pub other_stats: SolveStats,
+}
+
+/// Seed of attempt `attempt` of a parallel solve with `master_seed`.
+///
+/// Running a wave function with `SolverRng::seed_from_u64(attempt_seed(..))` repeats
+/// that attempt on its own.
+pub fn attempt_seed(master_seed: u64, attempt: usize) -> u64 {
+    let mut rng = SolverRng::seed_from_u64(master_seed);
+    rng.set_stream(attempt as u64);
+    rng.gen()
+}
+
+// state shared by the workers of one parallel solve
+struct Shared<T: Topology> {
+    next_attempt: AtomicUsize,
+    started_attempts: AtomicUsize,
+    // lowest attempt that succeeded so far, attempts above it are pointless
+    winner: AtomicUsize,
+    solved: Mutex<Option<(usize, WaveFunction<T>)>>,
+    // the failed or interrupted attempt that collapsed the most cells
+    best: Mutex<Option<WaveFunction<T>>>,
+    interrupted: Mutex<Option<GiveUpReason>>,
+    other_stats: Mutex<SolveStats>,
+}
+
+impl<T: Topology + Clone + Send + Sync> WaveFunction<T> {
+    /// Runs attempts on fresh copies of this wave function on `threads` worker
+    /// threads, like [`WaveFunction::solve`] does on one.
+    ///
+    /// Every attempt starts from the state the wave function is in, with its
+    /// restrictions and constraints. Attempt `i` is seeded with
+    /// [`attempt_seed`]`(master_seed, i)`. Of all attempts that succeed the one with
+    /// the lowest index is returned, so for a given master seed the result is the same
+    /// no matter how many threads are used or how they are scheduled, unless a timeout
+    /// or cancellation cuts the solve short. Once an attempt succeeds, attempts with a
+    /// higher index are cancelled.
+    pub fn solve_parallel(
+        &self,
+        master_seed: u64,
+        threads: usize,
+        limits: Limits,
+    ) -> Result<Solved<T>, GaveUp<T>> {
+        let start = self.snapshot();
+        let max_attempts = limits.max_attempts.unwrap_or(usize::MAX);
+        let started = Instant::now();
+        let shared = Shared {
+            next_attempt: AtomicUsize::new(0),
+            started_attempts: AtomicUsize::new(0),
+            winner: AtomicUsize::new(usize::MAX),
+            solved: Mutex::new(None),
+            best: Mutex::new(None),
+            interrupted: Mutex::new(None),
+            other_stats: Mutex::new(SolveStats::default()),
+        };
+        let interrupted = || {
+            if limits
+                .cancel
+                .as_ref()
+                .is_some_and(CancelToken::is_cancelled)
+            {
+                return Some(GiveUpReason::Cancelled);
+            }
+            match limits.timeout {
+                Some(timeout) if started.elapsed() >= timeout => {
+                    Some(GiveUpReason::Timeout(timeout))
+                }
+                _ => None,
+            }
+        };
+
+        thread::scope(|scope| {
+            for _ in 0..threads.max(1) {
+                scope.spawn(|| loop {
+                    let attempt = shared.next_attempt.fetch_add(1, Ordering::Relaxed);
+                    if attempt >= max_attempts
+                        || attempt > shared.winner.load(Ordering::Relaxed)
+                        || shared.interrupted.lock().unwrap().is_some()
+                    {
+                        break;
+                    }
+                    shared.started_attempts.fetch_add(1, Ordering::Relaxed);
+
+                    let mut rng = SolverRng::seed_from_u64(attempt_seed(master_seed, attempt));
+                    let mut wave_function = self.fresh_attempt(Some(&start));
+                    let mut failed = false;
+                    while !wave_function.done() {
+                        if attempt > shared.winner.load(Ordering::Relaxed) {
+                            failed = true;
+                            break;
+                        }
+                        if let Some(reason) = interrupted() {
+                            shared.interrupted.lock().unwrap().get_or_insert(reason);
+                            failed = true;
+                            break;
+                        }
+                        if wave_function.collapse(&mut rng).is_err() {
+                            if wave_function.history().len() > start.collapses() {
+                                wave_function.undo();
+                            } else {
+                                // nothing was collapsed, so every attempt fails like this
+                                shared
+                                    .interrupted
+                                    .lock()
+                                    .unwrap()
+                                    .get_or_insert(GiveUpReason::Unsatisfiable);
+                            }
+                            failed = true;
+                            break;
+                        }
+                    }
+
+                    if !failed {
+                        let mut solved = shared.solved.lock().unwrap();
+                        let mut other_stats = shared.other_stats.lock().unwrap();
+                        match &*solved {
+                            Some((winner, _)) if *winner < attempt => {
+                                *other_stats += wave_function.stats();
+                            }
+                            _ => {
+                                shared.winner.fetch_min(attempt, Ordering::Relaxed);
+                                if let Some((_, beaten)) = solved.replace((attempt, wave_function))
+                                {
+                                    *other_stats += beaten.stats();
+                                }
+                            }
+                        }
+                        continue;
+                    }
+
+                    *shared.other_stats.lock().unwrap() += wave_function.stats();
+                    let mut best = shared.best.lock().unwrap();
+                    let is_best = match &*best {
+                        Some(best) => wave_function.collapsed_cells() > best.collapsed_cells(),
+                        None => true,
+                    };
+                    if is_best {
+                        *best = Some(wave_function);
+                    }
+                });
+            }
+        });
+
+        let attempts = shared.started_attempts.into_inner();
+        if let Some((attempt, wave_function)) = shared.solved.into_inner().unwrap() {
+            return Ok(Solved {
+                wave_function,
+                attempt,
+                attempts,
+                seed: attempt_seed(master_seed, attempt),
+                other_stats: shared.other_stats.into_inner().unwrap(),
+            });
+        }
+        let reason = shared
+            .interrupted
+            .into_inner()
+            .unwrap()
+            .unwrap_or(GiveUpReason::MaxAttempts(max_attempts));
+        let best = match shared.best.into_inner().unwrap() {
+            Some(best) => best,
+            None => self.fresh_attempt(Some(&start)),
+        };
+        Err(GaveUp {
+            reason,
+            attempts,
+            best: Box::new(best),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wfc::{constraints::TileCount, tileset::fixtures};
+
+    const SHAPE: (usize, usize) = (12, 12);
+
+    fn solve_on(template: &WaveFunction, threads: usize) -> Solved {
+        let limits = Limits {
+            max_attempts: Some(200),
+            ..Limits::default()
+        };
+        template.solve_parallel(0, threads, limits).unwrap()
+    }
+
+    #[test]
+    fn winner_is_the_same_on_any_number_of_threads() {
+        // an exact count makes most attempts fail, so the winner isn't simply the first
+        let mut template = WaveFunction::new(SHAPE, fixtures::tileset());
+        template.add_tile_count(TileCount::exactly([0], 30));
+        let single = solve_on(&template, 1);
+        assert!(single.attempt > 0);
+        for threads in [2, 3, 8] {
+            let solved = solve_on(&template, threads);
+            assert_eq!(solved.attempt, single.attempt, "on {threads} threads");
+            assert_eq!(solved.seed, single.seed);
+            assert_eq!(
+                solved.wave_function.get_result(),
+                single.wave_function.get_result()
+            );
+        }
+    }
+
+    #[test]
+    fn winner_can_be_repeated_from_its_seed() {
+        let template = WaveFunction::new(SHAPE, fixtures::tileset());
+        let solved = solve_on(&template, 4);
+        let mut repeated = WaveFunction::new(SHAPE, fixtures::tileset());
+        repeated
+            .run(&mut SolverRng::seed_from_u64(solved.seed))
+            .unwrap();
+        assert_eq!(repeated.get_result(), solved.wave_function.get_result());
+    }
+
+    #[test]
+    fn attempts_keep_the_restrictions_and_constraints_of_the_template() {
+        let mut template = WaveFunction::new(SHAPE, fixtures::tileset());
+        template.restrict((5, 5), |tile| tile == 0).unwrap();
+        template.add_tile_count(TileCount::at_most([0], 60));
+        let solved = solve_on(&template, 4);
+        let result = solved.wave_function.get_result().unwrap();
+        assert_eq!(result[[5, 5]], 0);
+        assert!(result.iter().filter(|&&tile| tile == 0).count() <= 60);
+    }
+
+    #[test]
+    fn contradicted_template_is_unsatisfiable() {
+        let mut template = WaveFunction::new(SHAPE, fixtures::tileset());
+        template.add_tile_count(TileCount::at_least([0], SHAPE.0 * SHAPE.1 + 1));
+        let Err(gave_up) = template.solve_parallel(0, 4, Limits::default()) else {
+            panic!("solved a contradicted template");
+        };
+        assert_eq!(gave_up.reason, GiveUpReason::Unsatisfiable);
+    }
+}
@@ -0,0 +1,37 @@
+use image::{ImageResult, Rgb, RgbImage};
+
+use super::{
+    tileset::{draw_tile, TILE_SIZE},
+    topology::Topology,
+    WaveFunction,
+};
+
+// same gray the viewer shows for cells that aren't collapsed yet
+const UNCOLLAPSED: Rgb<u8> = Rgb([130, 130, 130]);
+
+impl<T: Topology> WaveFunction<T> {
+    /// Renders the output at one pixel per tile pixel, cells that aren't collapsed yet
+    /// are gray.
+    pub fn render(&self) -> RgbImage {
+        let [shape_x, shape_y] = self.shape;
+        let mut image = RgbImage::from_pixel(
+            (shape_x * TILE_SIZE) as u32,
+            (shape_y * TILE_SIZE) as u32,
+            UNCOLLAPSED,
+        );
+        for x in 0..shape_x {
+            for y in 0..shape_y {
+                if let Some(tile_id) = self.get_tile_id((x, y)) {
+                    let corner = ((x * TILE_SIZE) as u32, (y * TILE_SIZE) as u32);
+                    draw_tile(&mut image, &self.tileset[tile_id], corner, 1);
+                }
+            }
+        }
+        image
+    }
+
+    /// Writes [`WaveFunction::render`] to `path`, the format follows the extension.
+    pub fn save_image(&self, path: &str) -> ImageResult<()> {
+        self.render().save(path)
+    }
+}
@@ -0,0 +1,119 @@
+use super::{topology::Topology, Coordinates, EntropyField, WaveField, WaveFunction};
+
+/// Copy of the state of a [`WaveFunction`], taken with [`WaveFunction::snapshot`].
+///
+/// The tileset isn't part of it, a snapshot can only be restored into the wave
+/// function it was taken from or one over the same tileset and shape.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub(super) done: bool,
+    pub(super) entropy_field: EntropyField,
+    pub(super) wave_field: WaveField,
+    pub(super) history: Vec<(Coordinates, usize)>,
+    pub(super) bans: Vec<(Coordinates, usize)>,
+    pub(super) undo_marks: Vec<usize>,
+}
+
+impl Snapshot {
+    /// Number of collapses the wave function had gone through when the snapshot was taken.
+    pub fn collapses(&self) -> usize {
+        self.history.len()
+    }
+}
+
+impl<T: Topology> WaveFunction<T> {
+    /// Captures the current state so it can be brought back with [`WaveFunction::restore`].
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            done: self.done,
+            entropy_field: self.entropy_field.clone(),
+            wave_field: self.wave_field.clone(),
+            history: self.history.clone(),
+            bans: self.bans.clone(),
+            undo_marks: self.undo_marks.clone(),
+        }
+    }
+
+    /// Returns to the state captured in `snapshot`, including its undo log.
+    ///
+    /// Panics if the snapshot was taken from a wave function of a different shape or
+    /// number of tiles.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        assert_eq!(
+            self.wave_field.shape(),
+            snapshot.wave_field.shape(),
+            "snapshot doesn't fit this wave function"
+        );
+        self.done = snapshot.done;
+        self.entropy_field.assign(&snapshot.entropy_field);
+        self.wave_field.assign(&snapshot.wave_field);
+        self.history.clone_from(&snapshot.history);
+        self.bans.clone_from(&snapshot.bans);
+        self.undo_marks.clone_from(&snapshot.undo_marks);
+    }
+
+    /// Takes back the last collapse together with everything its propagation removed,
+    /// also after that propagation ran into a [`Contradiction`](super::Contradiction).
+    ///
+    /// Returns the cell and tile of the undone collapse, or `None` if there is nothing
+    /// left to undo. The cost is proportional to what the collapse changed.
+    pub fn undo(&mut self) -> Option<(Coordinates, usize)> {
+        let mark = self.undo_marks.pop()?;
+        for ((x, y), tile) in self.bans.drain(mark..) {
+            self.wave_field[[x, y, tile]] = true;
+            self.entropy_field[[x, y]] += 1;
+        }
+        self.done = false;
+        self.history.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::wfc::{tileset::fixtures, SolverRng, WaveFunction};
+
+    #[test]
+    fn restoring_a_snapshot_gives_back_the_exact_state() {
+        let mut rng = SolverRng::seed_from_u64(0);
+        let mut wave_function = WaveFunction::new((8, 6), fixtures::tileset());
+        for _ in 0..5 {
+            wave_function.collapse(&mut rng).unwrap();
+        }
+        let snapshot = wave_function.snapshot();
+        let (wave_field, entropy_field) = (
+            wave_function.wave_field.clone(),
+            wave_function.entropy_field.clone(),
+        );
+        let (history, bans) = (wave_function.history.clone(), wave_function.bans.clone());
+        let undo_marks = wave_function.undo_marks.clone();
+
+        wave_function.run(&mut rng).unwrap();
+        wave_function.restore(&snapshot);
+        assert_eq!(wave_function.wave_field, wave_field);
+        assert_eq!(wave_function.entropy_field, entropy_field);
+        assert_eq!(wave_function.history, history);
+        assert_eq!(wave_function.bans, bans);
+        assert_eq!(wave_function.undo_marks, undo_marks);
+        assert!(!wave_function.done());
+    }
+
+    #[test]
+    fn undo_takes_back_a_collapse_and_its_propagation() {
+        let mut rng = SolverRng::seed_from_u64(1);
+        let mut wave_function = WaveFunction::new((8, 6), fixtures::tileset());
+        for _ in 0..3 {
+            wave_function.collapse(&mut rng).unwrap();
+        }
+        let before = wave_function.snapshot();
+        wave_function.collapse(&mut rng).unwrap();
+        let collapsed = *wave_function.history.last().unwrap();
+
+        assert_eq!(wave_function.undo(), Some(collapsed));
+        assert_eq!(wave_function.wave_field, before.wave_field);
+        assert_eq!(wave_function.entropy_field, before.entropy_field);
+        assert_eq!(wave_function.history, before.history);
+        assert_eq!(wave_function.bans, before.bans);
+    }
+}
@@ -0,0 +1,104 @@
+use std::{fmt, ops::AddAssign};
+
+use serde::{Deserialize, Serialize};
+
+use super::{topology::Topology, WaveFunction};
+
+/// Counters of the work a [`WaveFunction`] has done, see [`WaveFunction::stats`].
+///
+/// Undoing or restoring doesn't take work back, the counters only go up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolveStats {
+    pub collapses: usize,
+    /// Cells taken off the propagation stack.
+    pub propagation_steps: usize,
+    /// Tiles removed from cells, by collapses and by propagation.
+    pub bans: usize,
+    pub contradictions: usize,
+}
+
+impl AddAssign for SolveStats {
+    fn add_assign(&mut self, other: Self) {
+        self.collapses += other.collapses;
+        self.propagation_steps += other.propagation_steps;
+        self.bans += other.bans;
+        self.contradictions += other.contradictions;
+    }
+}
+
+impl fmt::Display for SolveStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} collapses, {} propagation steps, {} bans, {} contradictions",
+            self.collapses, self.propagation_steps, self.bans, self.contradictions
+        )
+    }
+}
+
+impl<T: Topology> WaveFunction<T> {
+    /// Returns the work done on this wave function so far.
+    pub fn stats(&self) -> SolveStats {
+        self.stats
+    }
+
+    /// Returns how many collapsed cells hold each tile, indexed by tile id.
+    pub fn tile_usage(&self) -> Vec<usize> {
+        let mut usage = vec![0; self.tileset.len()];
+        for (coords, _) in self.entropy_field.indexed_iter() {
+            if let Some(tile_id) = self.get_tile_id(coords) {
+                usage[tile_id] += 1;
+            }
+        }
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::wfc::{
+        constraints::TileCount, observer::Observer, tileset::fixtures, Coordinates, SolverRng,
+    };
+
+    #[derive(Default)]
+    struct Events(SolveStats);
+
+    impl Observer for Events {
+        fn on_collapse(&mut self, _: Coordinates, _: usize) {
+            self.0.collapses += 1;
+        }
+        fn on_ban(&mut self, _: Coordinates, _: usize) {
+            self.0.bans += 1;
+        }
+        fn on_contradiction(&mut self, _: Coordinates) {
+            self.0.contradictions += 1;
+        }
+    }
+
+    #[test]
+    fn observed_events_match_the_counters() {
+        let mut contradicted = false;
+        for seed in 0..20 {
+            let mut wave_function = WaveFunction::new((8, 8), fixtures::tileset());
+            // an exact count makes some of the seeds run into a contradiction
+            wave_function.add_tile_count(TileCount::exactly([0], 20));
+            let before = wave_function.stats();
+            let mut events = Events::default();
+            contradicted |= wave_function
+                .run_with(&mut SolverRng::seed_from_u64(seed), &mut events)
+                .is_err();
+
+            let after = wave_function.stats();
+            assert_eq!(events.0.collapses, after.collapses - before.collapses);
+            assert_eq!(events.0.bans, after.bans - before.bans);
+            assert_eq!(
+                events.0.contradictions,
+                after.contradictions - before.contradictions
+            );
+        }
+        assert!(contradicted);
+    }
+}
@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+
+use super::{observer::Observer, topology::Topology, Coordinates, WaveFunction};
+
+/// A way to mirror a grid of cells or the pixels of a tile onto itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Mirror {
+    /// Left and right swap, the axis runs from top to bottom.
+    Horizontal,
+    /// Top and bottom swap, the axis runs from left to right.
+    Vertical,
+    /// A 180° rotation, which mirrors both ways at once.
+    HalfTurn,
+}
+
+impl Mirror {
+    /// Returns where the cell at `(x, y)` of a grid of `shape` ends up.
+    pub fn apply(self, (x, y): Coordinates, [width, height]: [usize; 2]) -> Coordinates {
+        match self {
+            Mirror::Horizontal => (width - 1 - x, y),
+            Mirror::Vertical => (x, height - 1 - y),
+            Mirror::HalfTurn => (width - 1 - x, height - 1 - y),
+        }
+    }
+}
+
+/// Symmetry of the whole output, see [`WaveFunction::require_symmetry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    /// The right half mirrors the left half.
+    Horizontal,
+    /// The lower half mirrors the upper half.
+    Vertical,
+    /// Every quarter mirrors its neighbouring quarters.
+    Both,
+    /// The output looks the same turned by 180°.
+    HalfTurn,
+}
+
+impl Symmetry {
+    /// The mirrors that map the output onto itself.
+    pub fn mirrors(self) -> &'static [Mirror] {
+        match self {
+            Symmetry::Horizontal => &[Mirror::Horizontal],
+            Symmetry::Vertical => &[Mirror::Vertical],
+            Symmetry::Both => &[Mirror::Horizontal, Mirror::Vertical, Mirror::HalfTurn],
+            Symmetry::HalfTurn => &[Mirror::HalfTurn],
+        }
+    }
+}
+
+// a mirror of the output with the tile every tile turns into under it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct MirrorRule {
+    mirror: Mirror,
+    counterparts: Vec<Option<usize>>,
+}
+
+impl MirrorRule {
+    pub(crate) fn fits(&self, num_tiles: usize) -> bool {
+        self.counterparts.len() == num_tiles
+            && self
+                .counterparts
+                .iter()
+                .flatten()
+                .all(|&tile| tile < num_tiles)
+    }
+}
+
+impl<T: Topology> WaveFunction<T> {
+    /// Requires the output to have `symmetry`: a cell holds the mirrored counterpart of
+    /// the tile in its mirrored cell, see [`Tileset::counterparts`].
+    ///
+    /// Tiles without a counterpart can't be used and cells on an axis only take tiles
+    /// that are their own counterpart. Mirroring has to keep neighbours next to each
+    /// other, which holds on square grids but not on [`Hex`](super::hex::Hex) maps.
+    ///
+    /// [`Tileset::counterparts`]: super::tileset::Tileset::counterparts
+    pub fn require_symmetry(&mut self, symmetry: Symmetry) {
+        for &mirror in symmetry.mirrors() {
+            let counterparts = self.tileset.counterparts(mirror);
+            self.constraints.symmetry.push(MirrorRule {
+                mirror,
+                counterparts,
+            });
+        }
+    }
+
+    /// Removes the tiles whose counterpart isn't possible in the mirrored cell anymore.
+    /// Returns the cells that changed.
+    pub(crate) fn apply_symmetry<O: Observer + ?Sized>(
+        &mut self,
+        rule: &MirrorRule,
+        observer: &mut O,
+    ) -> Vec<Coordinates> {
+        let [shape_x, shape_y] = self.shape;
+        let mut changed: Vec<Coordinates> = Vec::new();
+        for cell in (0..shape_x).flat_map(|x| (0..shape_y).map(move |y| (x, y))) {
+            let (mx, my) = rule.mirror.apply(cell, self.shape);
+            let remove: Vec<usize> = (0..self.tileset.len())
+                .filter(|&tile| self.wave_field[[cell.0, cell.1, tile]])
+                .filter(|&tile| match rule.counterparts[tile] {
+                    None => true,
+                    // on the axis the cell is its own mirror
+                    Some(counterpart) if (mx, my) == cell => counterpart != tile,
+                    Some(counterpart) => !self.wave_field[[mx, my, counterpart]],
+                })
+                .collect();
+            if !remove.is_empty() {
+                self.ban_where(cell, |tile| remove.contains(&tile), observer);
+                changed.push(cell);
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array2;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::wfc::{
+        limits::Limits,
+        tileset::{fixtures, WfcColor, TILE_SIZE},
+        SolverRng,
+    };
+
+    const SYMMETRIES: [Symmetry; 4] = [
+        Symmetry::Horizontal,
+        Symmetry::Vertical,
+        Symmetry::Both,
+        Symmetry::HalfTurn,
+    ];
+
+    fn solve(shape: (usize, usize), symmetry: Symmetry) -> WaveFunction {
+        let mut wave_function = WaveFunction::new(shape, fixtures::tileset());
+        wave_function.require_symmetry(symmetry);
+        let limits = Limits {
+            max_attempts: Some(100),
+            ..Limits::default()
+        };
+        let Ok(solved) = wave_function.solve(&mut SolverRng::seed_from_u64(5), limits, &mut ())
+        else {
+            panic!("no {symmetry:?} output of {shape:?} within 100 attempts");
+        };
+        solved
+    }
+
+    // the pixels of the output, indexed `[x, y]`
+    fn pixels(wave_function: &WaveFunction) -> Array2<WfcColor> {
+        let result = wave_function.get_result().unwrap();
+        let (width, height) = result.dim();
+        Array2::from_shape_fn((width * TILE_SIZE, height * TILE_SIZE), |(x, y)| {
+            let tile = result[[x / TILE_SIZE, y / TILE_SIZE]];
+            wave_function.get_tileset()[tile].get_image()[[x % TILE_SIZE, y % TILE_SIZE]]
+        })
+    }
+
+    #[test]
+    fn the_sample_has_tiles_that_change_when_mirrored() {
+        let tileset = fixtures::tileset();
+        for mirror in [Mirror::Horizontal, Mirror::Vertical, Mirror::HalfTurn] {
+            let counterparts = tileset.counterparts(mirror);
+            assert!(counterparts.iter().all(Option::is_some), "{mirror:?}");
+            assert!(
+                counterparts
+                    .iter()
+                    .enumerate()
+                    .any(|(tile, &counterpart)| counterpart != Some(tile)),
+                "{mirror:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn outputs_look_the_same_mirrored() {
+        // an odd size puts cells on the axes
+        for shape in [(8, 6), (7, 5)] {
+            for symmetry in SYMMETRIES {
+                let solved = solve(shape, symmetry);
+                let pixels = pixels(&solved);
+                let (width, height) = pixels.dim();
+                for &mirror in symmetry.mirrors() {
+                    for (coords, color) in pixels.indexed_iter() {
+                        assert_eq!(
+                            pixels[mirror.apply(coords, [width, height])],
+                            *color,
+                            "{symmetry:?} output of {shape:?} at {coords:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mirrored_cells_hold_counterparts() {
+        let solved = solve((8, 6), Symmetry::Both);
+        let result = solved.get_result().unwrap();
+        for &mirror in Symmetry::Both.mirrors() {
+            let counterparts = solved.get_tileset().counterparts(mirror);
+            for (cell, &tile) in result.indexed_iter() {
+                let mirrored = result[mirror.apply(cell, solved.shape())];
+                assert_eq!(Some(mirrored), counterparts[tile], "{mirror:?} of {cell:?}");
+            }
+        }
+    }
+}
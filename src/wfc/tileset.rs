@@ -1,30 +1,116 @@
-pub(crate) use wfc_color::WfcColor;
+pub use atlas::{AtlasOptions, AtlasRect};
+pub(crate) use export::draw_tile;
+pub(crate) use serialize::TilesetData;
+pub use serialize::TilesetError;
+pub use wfc_color::WfcColor;
 
 use image::{io::Reader, DynamicImage};
 use ndarray::{s, Array2, Array3, ArrayView2};
 
-use std::ops::{Index, IndexMut};
+use super::topology::{Square, Topology};
+
+use std::{
+    fmt,
+    ops::{Index, IndexMut},
+};
+
+mod atlas;
+mod edges;
+mod export;
+#[cfg(test)]
+pub(crate) mod fixtures;
+mod hex;
+mod manifest;
+mod mirror;
+mod serialize;
+
+/// Width and height of a tile in pixels.
+pub const TILE_SIZE: usize = 3;
+
+/// Side of a tile, `Left` and `Right` along `x`, `Up` and `Down` along `y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::Up,
+        Direction::Down,
+    ];
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
 
-pub(crate) const TILE_SIZE: usize = 3;
+    /// Returns the number of the direction in the [`Square`] topology and the ones
+    /// built on it.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Direction::Left => "left",
+            Direction::Right => "right",
+            Direction::Up => "up",
+            Direction::Down => "down",
+        };
+        write!(f, "{name}")
+    }
+}
 
+/// A `TILE_SIZE`×`TILE_SIZE` patch of the sample together with the ids of the
+/// tiles that may appear next to it.
+///
+/// `None` in one of the neighbour lists means the tile may touch the border of the
+/// output on that side. The weight is how often the tile was seen in the sample and
+/// biases how often it gets picked.
+///
+/// There is one neighbour list per direction of the [`Topology`] the tile was learned
+/// with, the first four are the sides in [`Direction`].
 #[derive(Clone)]
-pub(crate) struct Tile {
+pub struct Tile {
     image: Array2<WfcColor>,
-    left: Vec<Option<usize>>,
-    right: Vec<Option<usize>>,
-    up: Vec<Option<usize>>,
-    down: Vec<Option<usize>>,
+    weight: f64,
+    // indexed by direction
+    neighbours: Vec<Vec<Option<usize>>>,
 }
 impl Tile {
-    fn print(&self) -> () {
+    /// Creates a tile with one neighbour list per direction of its topology.
+    pub(crate) fn new(
+        image: Array2<WfcColor>,
+        weight: f64,
+        neighbours: Vec<Vec<Option<usize>>>,
+    ) -> Self {
+        Self {
+            image,
+            weight,
+            neighbours,
+        }
+    }
+    fn print(&self) {
         println!("image:");
         println!("{:?}", self.image);
-        println!("left: {:?}", self.left);
-        println!("right: {:?}", self.right);
-        println!("up: {:?}", self.up);
-        println!("down: {:?}", self.down);
+        println!("weight: {}", self.weight);
+        for (index, neighbours) in self.neighbours.iter().enumerate() {
+            match Direction::ALL.get(index) {
+                Some(direction) => println!("{direction}: {neighbours:?}"),
+                None => println!("direction {index}: {neighbours:?}"),
+            }
+        }
     }
-    fn from_ndarry_view(im_view: ArrayView2<WfcColor>) -> Self {
+    fn from_ndarray_view(im_view: ArrayView2<WfcColor>, directions: usize) -> Self {
         let mut image: Array2<WfcColor> =
             Array2::from_elem((TILE_SIZE, TILE_SIZE), WfcColor::black());
         for ((x, y), &c) in im_view.indexed_iter() {
@@ -33,66 +119,86 @@ impl Tile {
 
         Self {
             image,
-            left: Vec::new(),
-            right: Vec::new(),
-            up: Vec::new(),
-            down: Vec::new(),
+            weight: 1.0,
+            neighbours: vec![Vec::new(); directions],
         }
     }
 
-    fn add_left(&mut self, id: Option<usize>) {
-        if !self.left.contains(&id) {
-            self.left.push(id);
+    fn add_neighbour(&mut self, direction: usize, id: Option<usize>) {
+        let neighbours = &mut self.neighbours[direction];
+        if !neighbours.contains(&id) {
+            neighbours.push(id);
         }
     }
+    fn add_left(&mut self, id: Option<usize>) {
+        self.add_neighbour(Direction::Left.index(), id);
+    }
     fn add_right(&mut self, id: Option<usize>) {
-        if !self.right.contains(&id) {
-            self.right.push(id);
-        }
+        self.add_neighbour(Direction::Right.index(), id);
     }
     fn add_up(&mut self, id: Option<usize>) {
-        if !self.up.contains(&id) {
-            self.up.push(id);
-        }
+        self.add_neighbour(Direction::Up.index(), id);
     }
     fn add_down(&mut self, id: Option<usize>) {
-        if !self.down.contains(&id) {
-            self.down.push(id);
-        }
+        self.add_neighbour(Direction::Down.index(), id);
     }
 
-    pub(crate) fn is_left_border(&self) -> bool {
-        self.left.contains(&None)
+    /// Returns `true` if the tile may be placed on the left border of the output.
+    pub fn is_left_border(&self) -> bool {
+        self.is_border(Direction::Left.index())
     }
-    pub(crate) fn is_right_border(&self) -> bool {
-        self.right.contains(&None)
+    pub fn is_right_border(&self) -> bool {
+        self.is_border(Direction::Right.index())
     }
-    pub(crate) fn is_upper_border(&self) -> bool {
-        self.up.contains(&None)
+    pub fn is_upper_border(&self) -> bool {
+        self.is_border(Direction::Up.index())
     }
-    pub(crate) fn is_lower_border(&self) -> bool {
-        self.down.contains(&None)
+    pub fn is_lower_border(&self) -> bool {
+        self.is_border(Direction::Down.index())
+    }
+    /// Returns `true` if the tile may be placed where the output has no neighbour in
+    /// `direction` of its topology.
+    pub fn is_border(&self, direction: usize) -> bool {
+        self.neighbours[direction].contains(&None)
     }
 
-    pub(crate) fn get_left(&self) -> &Vec<Option<usize>> {
-        &self.left
+    /// Returns the ids of the tiles that may appear left of this one.
+    pub fn get_left(&self) -> &Vec<Option<usize>> {
+        self.get_neighbours(Direction::Left)
+    }
+    pub fn get_right(&self) -> &Vec<Option<usize>> {
+        self.get_neighbours(Direction::Right)
+    }
+    pub fn get_up(&self) -> &Vec<Option<usize>> {
+        self.get_neighbours(Direction::Up)
     }
-    pub(crate) fn get_right(&self) -> &Vec<Option<usize>> {
-        &self.right
+    pub fn get_down(&self) -> &Vec<Option<usize>> {
+        self.get_neighbours(Direction::Down)
     }
-    pub(crate) fn get_up(&self) -> &Vec<Option<usize>> {
-        &self.up
+
+    /// Returns the ids of the tiles that may appear on the `direction` side of this one.
+    pub fn get_neighbours(&self, direction: Direction) -> &Vec<Option<usize>> {
+        self.get_neighbours_in(direction.index())
+    }
+    /// Like [`Tile::get_neighbours`] with `direction` numbered by the tile's topology.
+    pub fn get_neighbours_in(&self, direction: usize) -> &Vec<Option<usize>> {
+        &self.neighbours[direction]
     }
-    pub(crate) fn get_down(&self) -> &Vec<Option<usize>> {
-        &self.down
+
+    /// Returns the relative frequency the tile is picked with.
+    pub fn get_weight(&self) -> f64 {
+        self.weight
     }
 
-    pub(crate) fn get_image(&self) -> ArrayView2<WfcColor> {
+    /// Returns the pixels of the tile.
+    pub fn get_image(&self) -> ArrayView2<'_, WfcColor> {
         self.image.slice(s![.., ..])
     }
 }
 
-pub(crate) struct Tileset {
+/// All tiles learned from a sample, indexed by tile id.
+#[derive(Clone)]
+pub struct Tileset {
     tiles: Vec<Tile>,
 }
 impl IndexMut<usize> for Tileset {
@@ -108,10 +214,32 @@ impl Index<usize> for Tileset {
     }
 }
 impl Tileset {
-    pub(crate) fn len(&self) -> usize {
+    /// Returns the number of tiles.
+    pub fn len(&self) -> usize {
         self.tiles.len()
     }
-    pub(crate) fn print(&self) -> () {
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+    /// Returns the number of directions of the [`Topology`] the tileset was learned with.
+    pub fn directions(&self) -> usize {
+        self.tiles
+            .first()
+            .map_or(Direction::ALL.len(), |tile| tile.neighbours.len())
+    }
+    /// Returns an error naming `feature` if the tileset was learned with other
+    /// directions than the four sides, which is all `feature` supports.
+    pub(crate) fn require_sides(&self, feature: &str) -> Result<(), TilesetError> {
+        if self.directions() != Direction::ALL.len() {
+            return Err(TilesetError::Invalid(format!(
+                "{feature} only supports tilesets with the four sides as directions, this one has {}",
+                self.directions()
+            )));
+        }
+        Ok(())
+    }
+    /// Prints every tile with its neighbours in a human readable form.
+    pub fn print(&self) {
         for i in 0..self.len() {
             let tile = &self[i];
             println!("Tile nr. {i}");
@@ -121,22 +249,22 @@ impl Tileset {
     fn empty() -> Self {
         Tileset { tiles: Vec::new() }
     }
-    fn contains(&self, im_view: ArrayView2<WfcColor>) -> bool {
-        for tile in &self.tiles {
-            if {
-                let ref this = tile;
-                im_view == this.image
-            } {
-                return true;
-            }
-        }
-        false
+    pub(crate) fn from_tiles(tiles: Vec<Tile>) -> Self {
+        Tileset { tiles }
     }
-    fn insert(&mut self, slice: ArrayView2<WfcColor>) -> () {
-        if !self.contains(slice) {
-            let id: usize = self.len();
-            let tile: Tile = Tile::from_ndarry_view(slice);
-            self.tiles.insert(id, tile)
+    fn insert(&mut self, slice: ArrayView2<WfcColor>, weight: f64, directions: usize) -> usize {
+        match self.get_id(slice) {
+            Some(id) => {
+                self[id].weight += weight;
+                id
+            }
+            None => {
+                let id: usize = self.len();
+                let mut tile: Tile = Tile::from_ndarray_view(slice, directions);
+                tile.weight = weight;
+                self.tiles.insert(id, tile);
+                id
+            }
         }
     }
     fn get_id(&self, im_view: ArrayView2<WfcColor>) -> Option<usize> {
@@ -148,151 +276,189 @@ impl Tileset {
 
         None
     }
-    pub(crate) fn from_png(path: &str) -> Self {
-        assert!(path.ends_with(".png"), "for now only pngs are supported");
-
-        fn get_image(path: &str) -> DynamicImage {
-            let reader = match Reader::open(path) {
-                Ok(reader) => reader,
-                Err(_) => panic!("not able to open file"),
-            };
-
-            match reader.decode() {
-                Ok(image) => image,
-                Err(_) => panic!("not able to decode file"),
-            }
+    /// Cuts the sample at `path` into tiles and records which tiles touch each other.
+    ///
+    /// The width and height of the image have to be multiples of [`TILE_SIZE`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file can't be read or isn't an RGB png.
+    pub fn from_png(path: &str) -> Self {
+        match Tileset::from_pngs(&[(path, 1.0)]) {
+            Ok(tileset) => tileset,
+            Err(err) => panic!("{err}"),
         }
-        fn get_image_as_array3((width, height): (u32, u32), im_data: &[u8]) -> Array3<u8> {
-            if im_data.len() as u32 != width * height * 3 {
-                if im_data.len() as u32 == width * height * 4 {
-                    panic!("alpha channel not yet supported")
-                }
-                panic!("image doesn't have the format 3 bytes per pixel")
+    }
+    /// Learns one tileset from several `(path, weight)` samples.
+    ///
+    /// Identical tiles from different samples become one tile whose weight is the sum
+    /// of its occurrences, each counted with the weight of its sample. Adjacency is only
+    /// recorded within a sample, so no pairs appear along the seams between them.
+    pub fn from_pngs(samples: &[(&str, f64)]) -> Result<Self, TilesetError> {
+        Tileset::from_pngs_in(samples, &Square)
+    }
+    /// Like [`Tileset::from_pngs`], recording which tiles touch in the samples by the
+    /// neighbours `topology` gives the cells of a sample.
+    ///
+    /// The tileset can then be solved with [`WaveFunction::with_topology`] and the same
+    /// topology, or any other one with the same directions.
+    ///
+    /// [`WaveFunction::with_topology`]: super::WaveFunction::with_topology
+    pub fn from_pngs_in<T: Topology + ?Sized>(
+        samples: &[(&str, f64)],
+        topology: &T,
+    ) -> Result<Self, TilesetError> {
+        let mut tileset = Tileset::empty();
+        for &(path, weight) in samples {
+            if !(weight.is_finite() && weight > 0.0) {
+                return Err(TilesetError::Invalid(format!(
+                    "{path}: sample weight must be positive, got {weight}"
+                )));
             }
-
-            let shape: (usize, usize, usize) = (width as usize, height as usize, 3);
-            let mut image: Array3<u8> = Array3::from_elem(shape, 0);
-
-            for x in 0..width as usize {
-                for y in 0..height as usize {
-                    for channel in 0..3 {
-                        let index: usize = 3 * (height as usize) * x + 3 * y + channel;
-                        image[[x, y, channel]] = im_data[index];
+            let image: Array2<WfcColor> = read_png(path)?;
+            tileset
+                .add_sample(&image, weight, topology)
+                .map_err(|err| match err {
+                    TilesetError::Invalid(reason) => {
+                        TilesetError::Invalid(format!("{path}: {reason}"))
                     }
-                }
-            }
-
-            image
+                    err => err,
+                })?;
         }
-        fn convert_array3u8_to_array2color(arr3: Array3<u8>) -> Array2<WfcColor> {
-            let shape: &[usize] = arr3.shape();
-            let width: usize = shape[0];
-            let height: usize = shape[1];
-            let mut arr2: Array2<WfcColor> = Array2::from_elem((width, height), WfcColor::black());
-            for x in 0..width {
-                for y in 0..height {
-                    arr2[[x, y]] = WfcColor::from_ndarry_view(arr3.slice(s![x, y, ..]));
-                }
-            }
-            arr2
+        Ok(tileset)
+    }
+    /// Cuts the png at `path` into tiles like a sample and returns the id of every
+    /// tile, indexed `[x, y]`. Fails if the image contains a tile that isn't in the
+    /// tileset, so it has to be an output of this tileset or a part of its samples.
+    pub fn identify_png(&self, path: &str) -> Result<Array2<usize>, TilesetError> {
+        let image: Array2<WfcColor> = read_png(path)?;
+        let [width, height] = cells_of(&image)?;
+        let mut ids: Array2<usize> = Array2::zeros((width, height));
+        for ((x, y), id) in ids.indexed_iter_mut() {
+            let (px, py) = (x * TILE_SIZE, y * TILE_SIZE);
+            let slice = image.slice(s![px..px + TILE_SIZE, py..py + TILE_SIZE]);
+            *id = self.get_id(slice).ok_or_else(|| {
+                TilesetError::Invalid(format!(
+                    "{path}: the tile at cell ({x}, {y}) isn't in the tileset"
+                ))
+            })?;
         }
-        fn generate_tile_set(image: Array2<WfcColor>) -> Tileset {
-            fn get_im_slice([x, y]: [usize; 2], image: &Array2<WfcColor>) -> ArrayView2<WfcColor> {
-                image.slice(s![x..x + 3, y..y + 3])
-            }
-            fn is_valid_coords(coords: [isize; 2], shape: &[usize]) -> bool {
-                let x = coords[0] as usize;
-                let y = coords[1] as usize;
-                return x <= (shape[0] - TILE_SIZE) && y <= (shape[1] - TILE_SIZE);
-            }
-            let shape = image.shape();
-            let width = shape[0];
-            let height = shape[1];
-            assert_eq!(
-                width % TILE_SIZE,
-                0,
-                "width of input image must be multiple of tilesieze ({})",
-                TILE_SIZE
-            );
-            assert_eq!(
-                height % TILE_SIZE,
-                0,
-                "height of input image must be multiple of tilesieze ({})",
-                TILE_SIZE
-            );
-            let mut tileset = Tileset::empty();
-
-            // creating all Tiles
-            for x in (0..width).step_by(TILE_SIZE) {
-                for y in (0..height).step_by(TILE_SIZE) {
-                    let slice: ArrayView2<WfcColor> = get_im_slice([x, y], &image);
-                    tileset.insert(slice);
-                }
+        Ok(ids)
+    }
+    fn add_sample<T: Topology + ?Sized>(
+        &mut self,
+        image: &Array2<WfcColor>,
+        weight: f64,
+        topology: &T,
+    ) -> Result<(), TilesetError> {
+        // creating all Tiles
+        let cells = cells_of(image)?;
+        let ids: Array2<usize> = Array2::from_shape_fn((cells[0], cells[1]), |(x, y)| {
+            let (x, y) = (x * TILE_SIZE, y * TILE_SIZE);
+            let slice: ArrayView2<WfcColor> = image.slice(s![x..x + TILE_SIZE, y..y + TILE_SIZE]);
+            self.insert(slice, weight, topology.directions())
+        });
+
+        // computing neighbours, `None` where the sample ends in that direction
+        for (coords, &tile_id) in ids.indexed_iter() {
+            for direction in 0..topology.directions() {
+                let neighbour_id = topology
+                    .neighbour(coords, direction, cells)
+                    .map(|neighbour| ids[neighbour]);
+                self[tile_id].add_neighbour(direction, neighbour_id);
             }
+        }
 
-            // computing neighbours
-            for x in (0..width).step_by(TILE_SIZE) {
-                for y in (0..height).step_by(TILE_SIZE) {
-                    let slice: ArrayView2<WfcColor> = get_im_slice([x, y], &image);
-                    let tile_id = tileset.get_id(slice).unwrap();
-
-                    // go left
-                    if is_valid_coords([x as isize - TILE_SIZE as isize, y as isize], shape) {
-                        let left: ArrayView2<WfcColor> = get_im_slice([x - TILE_SIZE, y], &image);
-                        let left_id = tileset.get_id(left).unwrap();
-                        tileset[tile_id].add_left(Some(left_id));
-                    } else {
-                        tileset[tile_id].add_left(None);
-                    }
+        Ok(())
+    }
+}
 
-                    // go right
-                    if is_valid_coords([x as isize + TILE_SIZE as isize, y as isize], shape) {
-                        let right: ArrayView2<WfcColor> = get_im_slice([x + TILE_SIZE, y], &image);
-                        let right_id = tileset.get_id(right).unwrap();
-                        tileset[tile_id].add_right(Some(right_id));
-                    } else {
-                        tileset[tile_id].add_right(None);
-                    }
+// number of tiles in each direction, the image has to be cut into whole tiles
+fn cells_of(image: &Array2<WfcColor>) -> Result<[usize; 2], TilesetError> {
+    let shape = image.shape();
+    let width = shape[0];
+    let height = shape[1];
+    if width == 0 || width % TILE_SIZE != 0 {
+        return Err(TilesetError::Invalid(format!(
+            "width of input image must be multiple of tilesieze ({TILE_SIZE})"
+        )));
+    }
+    if height == 0 || height % TILE_SIZE != 0 {
+        return Err(TilesetError::Invalid(format!(
+            "height of input image must be multiple of tilesieze ({TILE_SIZE})"
+        )));
+    }
+    Ok([width / TILE_SIZE, height / TILE_SIZE])
+}
 
-                    // go up
-                    if is_valid_coords([x as isize, y as isize - TILE_SIZE as isize], shape) {
-                        let up: ArrayView2<WfcColor> = get_im_slice([x, y - TILE_SIZE], &image);
-                        let up_id = tileset.get_id(up).unwrap();
-                        tileset[tile_id].add_up(Some(up_id));
-                    } else {
-                        tileset[tile_id].add_up(None);
-                    }
+/// Reads the png at `path` into an array indexed `[x, y]`, with `x` going right and `y` down.
+pub(crate) fn read_png(path: &str) -> Result<Array2<WfcColor>, TilesetError> {
+    fn get_image_as_array3(
+        (width, height): (u32, u32),
+        im_data: &[u8],
+    ) -> Result<Array3<u8>, TilesetError> {
+        if im_data.len() as u32 != width * height * 3 {
+            if im_data.len() as u32 == width * height * 4 {
+                return Err(TilesetError::Invalid(
+                    "alpha channel not yet supported".to_string(),
+                ));
+            }
+            return Err(TilesetError::Invalid(
+                "image doesn't have the format 3 bytes per pixel".to_string(),
+            ));
+        }
 
-                    // go down
-                    if is_valid_coords([x as isize, y as isize + TILE_SIZE as isize], shape) {
-                        let down: ArrayView2<WfcColor> = get_im_slice([x, y + TILE_SIZE], &image);
-                        let down_id = tileset.get_id(down).unwrap();
-                        tileset[tile_id].add_down(Some(down_id));
-                    } else {
-                        tileset[tile_id].add_down(None);
-                    }
+        let shape: (usize, usize, usize) = (width as usize, height as usize, 3);
+        let mut image: Array3<u8> = Array3::from_elem(shape, 0);
+
+        // pixel data is stored row by row
+        for x in 0..width as usize {
+            for y in 0..height as usize {
+                for channel in 0..3 {
+                    let index: usize = 3 * (width as usize) * y + 3 * x + channel;
+                    image[[x, y, channel]] = im_data[index];
                 }
             }
-
-            return tileset;
         }
-        let image: DynamicImage = get_image(path);
-        let im_shape: (u32, u32) = (image.width(), image.height());
 
-        let image_as_array3: Array3<u8> = get_image_as_array3(im_shape, image.as_bytes());
-        let image: Array2<WfcColor> = convert_array3u8_to_array2color(image_as_array3);
+        Ok(image)
+    }
+    fn convert_array3u8_to_array2color(arr3: Array3<u8>) -> Array2<WfcColor> {
+        let shape: &[usize] = arr3.shape();
+        let width: usize = shape[0];
+        let height: usize = shape[1];
+        let mut arr2: Array2<WfcColor> = Array2::from_elem((width, height), WfcColor::black());
+        for x in 0..width {
+            for y in 0..height {
+                arr2[[x, y]] = WfcColor::from_ndarray_view(arr3.slice(s![x, y, ..]));
+            }
+        }
+        arr2
+    }
 
-        generate_tile_set(image)
+    if !path.ends_with(".png") {
+        return Err(TilesetError::Invalid(format!(
+            "{path}: for now only pngs are supported"
+        )));
     }
+    let image: DynamicImage = Reader::open(path)?.decode()?;
+    let im_shape: (u32, u32) = (image.width(), image.height());
+
+    let image_as_array3: Array3<u8> = get_image_as_array3(im_shape, image.as_bytes())?;
+    Ok(convert_array3u8_to_array2color(image_as_array3))
 }
 
 mod wfc_color {
-    use std::fmt::Debug;
+    use std::{fmt::Debug, str::FromStr};
 
     use ndarray::ArrayView1;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
+    /// RGB color of a single pixel.
+    ///
+    /// Serialized as a `"#rrggbb"` string in human readable formats and as three bytes otherwise.
     #[derive(Clone, Copy, PartialEq, Eq)]
-    pub(crate) struct WfcColor {
+    pub struct WfcColor {
         r: u8,
         g: u8,
         b: u8,
@@ -312,8 +478,65 @@ mod wfc_color {
         }
     }
 
+    impl From<[u8; 3]> for WfcColor {
+        fn from([r, g, b]: [u8; 3]) -> Self {
+            Self { r, g, b }
+        }
+    }
+
+    impl From<WfcColor> for [u8; 3] {
+        fn from(color: WfcColor) -> Self {
+            [color.r, color.g, color.b]
+        }
+    }
+
+    impl Serialize for WfcColor {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b).serialize(serializer)
+            } else {
+                <[u8; 3]>::from(*self).serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for WfcColor {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if !deserializer.is_human_readable() {
+                return <[u8; 3]>::deserialize(deserializer).map(WfcColor::from);
+            }
+            let hex = String::deserialize(deserializer)?;
+            hex.parse().map_err(de::Error::custom)
+        }
+    }
+
+    impl FromStr for WfcColor {
+        type Err = String;
+
+        /// Parses a `"#rrggbb"` string.
+        fn from_str(hex: &str) -> Result<Self, Self::Err> {
+            let digits = hex
+                .strip_prefix('#')
+                .filter(|digits| digits.len() == 6 && digits.is_ascii())
+                .ok_or_else(|| format!("expected \"#rrggbb\", got {hex:?}"))?;
+            let channel = |i: usize| {
+                u8::from_str_radix(&digits[2 * i..2 * i + 2], 16)
+                    .map_err(|_| format!("invalid hex color {hex:?}"))
+            };
+            Ok(Self {
+                r: channel(0)?,
+                g: channel(1)?,
+                b: channel(2)?,
+            })
+        }
+    }
+
     impl WfcColor {
-        pub(crate) fn from_ndarry_view(arr_view: ArrayView1<u8>) -> Self {
+        pub fn new(r: u8, g: u8, b: u8) -> Self {
+            Self { r, g, b }
+        }
+
+        pub fn from_ndarray_view(arr_view: ArrayView1<u8>) -> Self {
             Self {
                 r: arr_view[0],
                 g: arr_view[1],
@@ -321,12 +544,56 @@ mod wfc_color {
             }
         }
 
-        pub(crate) fn black() -> Self {
+        pub fn black() -> Self {
             Self { r: 0, g: 0, b: 0 }
         }
 
-        pub(crate) fn get(&self) -> (u8, u8, u8) {
+        /// Returns the `(r, g, b)` channels.
+        pub fn get(&self) -> (u8, u8, u8) {
             (self.r, self.g, self.b)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn non_square_pngs_are_not_transposed() {
+        // two tiles side by side, every pixel with a color of its own
+        let (width, height) = (2 * TILE_SIZE, TILE_SIZE);
+        let color = |x: usize, y: usize| WfcColor::new(x as u8 * 40, y as u8 * 80, 0);
+        let path = env::temp_dir()
+            .join(format!("tiling-wfc-{}-wide.png", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        RgbImage::from_fn(width as u32, height as u32, |x, y| {
+            let (r, g, b) = color(x as usize, y as usize).get();
+            Rgb([r, g, b])
+        })
+        .save(&path)
+        .unwrap();
+        let image = read_png(&path);
+        let tileset = Tileset::from_pngs(&[(&path, 1.0)]);
+        let ids = tileset
+            .as_ref()
+            .ok()
+            .map(|tileset| tileset.identify_png(&path));
+        fs::remove_file(&path).unwrap();
+
+        let image = image.unwrap();
+        assert_eq!(image.dim(), (width, height));
+        for ((x, y), &pixel) in image.indexed_iter() {
+            assert!(pixel == color(x, y), "pixel at ({x}, {y})");
+        }
+        let tileset = tileset.unwrap();
+        assert_eq!(tileset.len(), 2);
+        assert_eq!(tileset[0].get_right(), &[Some(1)]);
+        assert_eq!(ids.unwrap().unwrap(), ndarray::arr2(&[[0], [1]]));
+    }
+}
@@ -0,0 +1,228 @@
+use image::{Rgb, RgbImage};
+use serde::Serialize;
+
+use super::{export::draw_tile, Tileset, TilesetError, TILE_SIZE};
+
+const ATLAS_BACKGROUND: Rgb<u8> = Rgb([48, 48, 48]);
+const ATLAS_GRID: Rgb<u8> = Rgb([255, 0, 255]);
+const ATLAS_TEXT: Rgb<u8> = Rgb([230, 230, 230]);
+
+// 3x5 bitmap digits, one row per byte, the lowest 3 bits from left to right
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b011, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const DIGIT_WIDTH: u32 = 3;
+const DIGIT_HEIGHT: u32 = 5;
+
+/// Layout of [`Tileset::atlas`].
+#[derive(Debug, Clone, Copy)]
+pub struct AtlasOptions {
+    /// Every tile pixel becomes a `scale` x `scale` square.
+    pub scale: u32,
+    /// Tiles per row, a roughly square atlas if `None`.
+    pub columns: Option<usize>,
+    /// Separate the tiles with one pixel wide lines.
+    pub grid: bool,
+    /// Print the id of every tile below it.
+    pub labels: bool,
+}
+impl Default for AtlasOptions {
+    fn default() -> Self {
+        AtlasOptions {
+            scale: 1,
+            columns: None,
+            grid: false,
+            labels: false,
+        }
+    }
+}
+
+/// Where a tile ended up in the atlas, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AtlasRect {
+    pub id: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Serialize)]
+struct AtlasSidecar<'a> {
+    image: &'a str,
+    tile_size: usize,
+    scale: u32,
+    tiles: &'a [AtlasRect],
+}
+
+impl Tileset {
+    /// Draws every tile into one image in id order, left to right and top to bottom.
+    /// Returns the image together with the rectangle each tile occupies, indexed by id.
+    pub fn atlas(&self, options: AtlasOptions) -> (RgbImage, Vec<AtlasRect>) {
+        let scale = options.scale.max(1);
+        let tile = TILE_SIZE as u32 * scale;
+        let columns = options
+            .columns
+            .unwrap_or_else(|| (self.len() as f64).sqrt().ceil() as usize)
+            .max(1);
+        let rows = self.len().div_ceil(columns).max(1);
+
+        // the label scale is picked so the largest id fits below the tile
+        let (label_scale, label_height) = if options.labels {
+            let digits = self.len().saturating_sub(1).to_string().len() as u32;
+            let label_width = digits * (DIGIT_WIDTH + 1) - 1;
+            let label_scale = (tile / label_width).clamp(1, 2);
+            (label_scale, (DIGIT_HEIGHT + 2) * label_scale)
+        } else {
+            (0, 0)
+        };
+        let label_width =
+            |id: usize| (id.to_string().len() as u32 * (DIGIT_WIDTH + 1) - 1) * label_scale;
+        let cell_width = if options.labels {
+            tile.max(label_width(self.len().saturating_sub(1)))
+        } else {
+            tile
+        };
+        let cell_height = tile + label_height;
+        let gap = options.grid as u32;
+
+        let width = columns as u32 * (cell_width + gap) + gap;
+        let height = rows as u32 * (cell_height + gap) + gap;
+        let mut atlas = RgbImage::from_pixel(width, height, ATLAS_BACKGROUND);
+
+        if options.grid {
+            for x in (0..width).step_by((cell_width + gap) as usize) {
+                for y in 0..height {
+                    atlas.put_pixel(x, y, ATLAS_GRID);
+                }
+            }
+            for y in (0..height).step_by((cell_height + gap) as usize) {
+                for x in 0..width {
+                    atlas.put_pixel(x, y, ATLAS_GRID);
+                }
+            }
+        }
+
+        let mut rects = Vec::with_capacity(self.len());
+        for (id, tile_data) in self.tiles.iter().enumerate() {
+            let left = gap + (id % columns) as u32 * (cell_width + gap);
+            let top = gap + (id / columns) as u32 * (cell_height + gap);
+            draw_tile(&mut atlas, tile_data, (left, top), scale);
+            rects.push(AtlasRect {
+                id,
+                x: left,
+                y: top,
+                width: tile,
+                height: tile,
+            });
+            if options.labels {
+                draw_number(
+                    &mut atlas,
+                    id,
+                    (left, top + tile + label_scale),
+                    label_scale,
+                );
+            }
+        }
+        (atlas, rects)
+    }
+
+    /// Writes [`Tileset::atlas`] to `path` and the tile rectangles as JSON next to it,
+    /// `atlas.png` gets `atlas.json`.
+    pub fn save_atlas(&self, path: &str, options: AtlasOptions) -> Result<(), TilesetError> {
+        let (atlas, rects) = self.atlas(options);
+        atlas.save(path)?;
+        let sidecar = AtlasSidecar {
+            image: std::path::Path::new(path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or(path),
+            tile_size: TILE_SIZE,
+            scale: options.scale.max(1),
+            tiles: &rects,
+        };
+        let sidecar_path = std::path::Path::new(path).with_extension("json");
+        std::fs::write(sidecar_path, serde_json::to_string_pretty(&sidecar)?)?;
+        Ok(())
+    }
+}
+
+fn draw_number(image: &mut RgbImage, number: usize, (left, top): (u32, u32), scale: u32) {
+    for (index, digit) in number.to_string().bytes().enumerate() {
+        let glyph = DIGITS[(digit - b'0') as usize];
+        let glyph_left = left + index as u32 * (DIGIT_WIDTH + 1) * scale;
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..DIGIT_WIDTH {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for dx in 0..scale {
+                    for dy in 0..scale {
+                        image.put_pixel(
+                            glyph_left + column * scale + dx,
+                            top + row as u32 * scale + dy,
+                            ATLAS_TEXT,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+    use crate::wfc::tileset::fixtures;
+
+    #[test]
+    fn sidecar_points_at_the_pixels_of_each_tile() {
+        let tileset = fixtures::tileset();
+        let options = AtlasOptions {
+            scale: 2,
+            columns: Some(3),
+            grid: true,
+            labels: true,
+        };
+        let dir = env::temp_dir().join(format!("tiling-wfc-{}-atlas", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("atlas.png").to_string_lossy().into_owned();
+        tileset.save_atlas(&path, options).unwrap();
+        let atlas = image::open(&path).unwrap().to_rgb8();
+        let sidecar: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("atlas.json")).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(sidecar["image"], "atlas.png");
+        let rects = sidecar["tiles"].as_array().unwrap();
+        assert_eq!(rects.len(), tileset.len());
+        let field = |rect: &serde_json::Value, name: &str| rect[name].as_u64().unwrap() as u32;
+        for (id, rect) in rects.iter().enumerate() {
+            assert_eq!(field(rect, "id"), id as u32);
+            let (left, top) = (field(rect, "x"), field(rect, "y"));
+            assert_eq!(field(rect, "width"), TILE_SIZE as u32 * options.scale);
+            assert_eq!(field(rect, "height"), TILE_SIZE as u32 * options.scale);
+            for ((x, y), color) in tileset[id].get_image().indexed_iter() {
+                let (r, g, b) = color.get();
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let pixel = atlas.get_pixel(
+                        left + x as u32 * options.scale + dx,
+                        top + y as u32 * options.scale + dy,
+                    );
+                    assert_eq!(pixel, &Rgb([r, g, b]), "pixel ({x}, {y}) of tile {id}");
+                }
+            }
+        }
+    }
+}
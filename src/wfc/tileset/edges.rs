@@ -0,0 +1,121 @@
+use ndarray::{s, ArrayView1};
+
+use super::{Direction, Tile, Tileset, TilesetError, WfcColor, TILE_SIZE};
+
+fn edges_match(a: ArrayView1<WfcColor>, b: ArrayView1<WfcColor>, tolerance: u8) -> bool {
+    a.iter().zip(b.iter()).all(|(a, b)| {
+        let (a, b) = (a.get(), b.get());
+        a.0.abs_diff(b.0) <= tolerance
+            && a.1.abs_diff(b.1) <= tolerance
+            && a.2.abs_diff(b.2) <= tolerance
+    })
+}
+
+impl Tile {
+    fn left_edge(&self) -> ArrayView1<'_, WfcColor> {
+        self.image.slice(s![0, ..])
+    }
+    fn right_edge(&self) -> ArrayView1<'_, WfcColor> {
+        self.image.slice(s![TILE_SIZE - 1, ..])
+    }
+    fn upper_edge(&self) -> ArrayView1<'_, WfcColor> {
+        self.image.slice(s![.., 0])
+    }
+    fn lower_edge(&self) -> ArrayView1<'_, WfcColor> {
+        self.image.slice(s![.., TILE_SIZE - 1])
+    }
+}
+
+impl Tileset {
+    /// Replaces the neighbour lists with ones derived from the tile borders: two tiles
+    /// may touch if their facing edge pixels differ by at most `tolerance` in every
+    /// channel, whether or not that pair was ever seen next to each other.
+    ///
+    /// Which tiles may touch the border of the output is kept as it was. Returns
+    /// [`TilesetError::Invalid`] for tilesets learned with other directions than the
+    /// four sides, their edges don't face each other the same way.
+    pub fn use_edge_adjacency(&mut self, tolerance: u8) -> Result<(), TilesetError> {
+        self.require_sides("edge adjacency")?;
+        for tile in &mut self.tiles {
+            for direction in Direction::ALL {
+                tile.neighbours[direction.index()].retain(Option::is_none);
+            }
+        }
+
+        for id in 0..self.len() {
+            for other_id in 0..self.len() {
+                // `other_id` right of `id`
+                if edges_match(self[id].right_edge(), self[other_id].left_edge(), tolerance) {
+                    self[id].add_right(Some(other_id));
+                    self[other_id].add_left(Some(id));
+                }
+                // `other_id` below `id`
+                if edges_match(
+                    self[id].lower_edge(),
+                    self[other_id].upper_edge(),
+                    tolerance,
+                ) {
+                    self[id].add_down(Some(other_id));
+                    self[other_id].add_up(Some(id));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array2;
+
+    use super::*;
+
+    // a tile with `left` as its left column, `right` as its right column and gray between
+    fn tile(left: WfcColor, right: WfcColor, neighbours: Vec<Vec<Option<usize>>>) -> Tile {
+        let gray = WfcColor::new(128, 128, 128);
+        let image = Array2::from_shape_fn((TILE_SIZE, TILE_SIZE), |(x, _)| match x {
+            0 => left,
+            x if x == TILE_SIZE - 1 => right,
+            _ => gray,
+        });
+        Tile::new(image, 1.0, neighbours)
+    }
+
+    #[test]
+    fn tiles_touch_where_their_edges_match() {
+        let black = WfcColor::new(0, 0, 0);
+        let white = WfcColor::new(255, 255, 255);
+        let off_white = WfcColor::new(250, 255, 255);
+        let edge_tileset = |tolerance: u8| {
+            let mut tileset = Tileset::from_tiles(vec![
+                // seen to its own right in the sample, which the edges don't allow, and
+                // allowed on the left border
+                tile(
+                    black,
+                    white,
+                    vec![vec![None], vec![Some(0)], vec![], vec![]],
+                ),
+                tile(off_white, black, vec![Vec::new(); 4]),
+            ]);
+            tileset.use_edge_adjacency(tolerance).unwrap();
+            tileset
+        };
+
+        let tileset = edge_tileset(5);
+        assert_eq!(tileset[0].get_left(), &[None, Some(1)]);
+        assert_eq!(tileset[0].get_right(), &[Some(1)]);
+        assert_eq!(tileset[1].get_left(), &[Some(0)]);
+        assert_eq!(tileset[1].get_right(), &[Some(0)]);
+        // upper and lower edges are the same column by column, so each tile only
+        // stacks on itself
+        for id in 0..2 {
+            assert_eq!(tileset[id].get_up(), &[Some(id)]);
+            assert_eq!(tileset[id].get_down(), &[Some(id)]);
+        }
+
+        let tileset = edge_tileset(4);
+        assert_eq!(tileset[0].get_right(), &[] as &[Option<usize>]);
+        assert_eq!(tileset[1].get_left(), &[] as &[Option<usize>]);
+        assert_eq!(tileset[1].get_right(), &[Some(0)]);
+    }
+}
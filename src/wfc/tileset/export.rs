@@ -0,0 +1,204 @@
+use std::fmt::Write;
+
+use image::{Rgb, RgbImage};
+
+use super::{Direction, Tile, Tileset, TilesetError, WfcColor, TILE_SIZE};
+
+// how much tiles are enlarged on the rule sheet, and the gaps between them
+const SHEET_SCALE: u32 = 8;
+const SHEET_PADDING: u32 = 4;
+const SHEET_BACKGROUND: [Rgb<u8>; 2] = [Rgb([48, 48, 48]), Rgb([64, 64, 64])];
+const SHEET_FOREGROUND: Rgb<u8> = Rgb([230, 230, 230]);
+
+fn direction_color(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Left => "red",
+        Direction::Right => "blue",
+        Direction::Up => "darkgreen",
+        Direction::Down => "orange",
+    }
+}
+
+impl Tile {
+    /// Average color of the tile, used to fill its node in the DOT graph.
+    fn mean_color(&self) -> WfcColor {
+        let (mut r, mut g, mut b) = (0usize, 0usize, 0usize);
+        for color in self.image.iter() {
+            let (cr, cg, cb) = color.get();
+            r += cr as usize;
+            g += cg as usize;
+            b += cb as usize;
+        }
+        let n = self.image.len();
+        WfcColor::new((r / n) as u8, (g / n) as u8, (b / n) as u8)
+    }
+}
+
+impl Tileset {
+    /// Writes the adjacency as a Graphviz DOT graph.
+    ///
+    /// Every tile is a node filled with its average color, every edge `a -> b` is
+    /// labelled with the side of `a` that `b` may appear on. Sides on which a tile
+    /// may touch the border of the output are listed in its label.
+    ///
+    /// Returns [`TilesetError::Invalid`] for tilesets learned with other directions
+    /// than the four sides.
+    pub fn to_dot(&self) -> Result<String, TilesetError> {
+        self.require_sides("the DOT export")?;
+        let mut dot = String::from("digraph tileset {\n    node [style=filled, shape=box];\n");
+        for (tile_id, tile) in self.tiles.iter().enumerate() {
+            let (r, g, b) = tile.mean_color().get();
+            let font_color = if (r as u32 + g as u32 + b as u32) < 3 * 128 {
+                "white"
+            } else {
+                "black"
+            };
+            let borders: Vec<String> = Direction::ALL
+                .into_iter()
+                .filter(|&direction| tile.get_neighbours(direction).contains(&None))
+                .map(|direction| direction.to_string())
+                .collect();
+            let label = if borders.is_empty() {
+                format!("{tile_id}")
+            } else {
+                format!("{tile_id}\\nborder: {}", borders.join(" "))
+            };
+            writeln!(
+                dot,
+                "    {tile_id} [label=\"{label}\", fillcolor=\"#{r:02x}{g:02x}{b:02x}\", fontcolor={font_color}];"
+            )
+            .unwrap();
+        }
+        for (tile_id, tile) in self.tiles.iter().enumerate() {
+            for direction in Direction::ALL {
+                for other_id in tile.get_neighbours(direction).iter().flatten() {
+                    writeln!(
+                        dot,
+                        "    {tile_id} -> {other_id} [label={direction}, color={}, fontcolor={}];",
+                        direction_color(direction),
+                        direction_color(direction)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+
+    /// Renders a rule sheet with one row per tile: the tile itself, followed by the
+    /// tiles allowed on each of its sides, every group introduced by an arrow pointing
+    /// to that side. A hatched square stands for the border of the output.
+    ///
+    /// Returns [`TilesetError::Invalid`] like [`Tileset::to_dot`].
+    pub fn rule_sheet(&self) -> Result<RgbImage, TilesetError> {
+        self.require_sides("the rule sheet")?;
+        let thumb = TILE_SIZE as u32 * SHEET_SCALE;
+        let row_height = thumb + 2 * SHEET_PADDING;
+        // mirrors the layout below
+        let row_width = |tile: &Tile| {
+            let groups: u32 = Direction::ALL
+                .into_iter()
+                .map(|direction| {
+                    (1 + tile.get_neighbours(direction).len() as u32) * (thumb + SHEET_PADDING)
+                        + 2 * SHEET_PADDING
+                })
+                .sum();
+            SHEET_PADDING + thumb + 3 * SHEET_PADDING + groups
+        };
+        let width = self.tiles.iter().map(row_width).max().unwrap_or(1);
+        let height = (row_height * self.len() as u32).max(1);
+        let mut sheet = RgbImage::new(width, height);
+
+        for (tile_id, tile) in self.tiles.iter().enumerate() {
+            let top = row_height * tile_id as u32;
+            for y in top..top + row_height {
+                for x in 0..width {
+                    sheet.put_pixel(x, y, SHEET_BACKGROUND[tile_id % 2]);
+                }
+            }
+
+            let y = top + SHEET_PADDING;
+            let mut x = SHEET_PADDING;
+            draw_tile(&mut sheet, tile, (x, y), SHEET_SCALE);
+            x += thumb + 3 * SHEET_PADDING;
+            for direction in Direction::ALL {
+                draw_arrow(&mut sheet, direction, (x, y), thumb);
+                x += thumb + SHEET_PADDING;
+                for neighbour in tile.get_neighbours(direction) {
+                    match neighbour {
+                        Some(other_id) => {
+                            draw_tile(&mut sheet, &self[*other_id], (x, y), SHEET_SCALE)
+                        }
+                        None => draw_border(&mut sheet, (x, y), thumb),
+                    }
+                    x += thumb + SHEET_PADDING;
+                }
+                x += 2 * SHEET_PADDING;
+            }
+        }
+        Ok(sheet)
+    }
+
+    /// Writes [`Tileset::to_dot`] to `path`.
+    pub fn save_dot(&self, path: &str) -> Result<(), TilesetError> {
+        std::fs::write(path, self.to_dot()?)?;
+        Ok(())
+    }
+
+    /// Writes [`Tileset::rule_sheet`] to `path`, the format follows the extension.
+    pub fn save_rule_sheet(&self, path: &str) -> Result<(), TilesetError> {
+        self.rule_sheet()?.save(path)?;
+        Ok(())
+    }
+}
+
+/// Draws `tile` enlarged by `scale` with its top left corner at `(left, top)`.
+pub(crate) fn draw_tile(image: &mut RgbImage, tile: &Tile, (left, top): (u32, u32), scale: u32) {
+    for ((x, y), color) in tile.get_image().indexed_iter() {
+        let (r, g, b) = color.get();
+        for dx in 0..scale {
+            for dy in 0..scale {
+                image.put_pixel(
+                    left + x as u32 * scale + dx,
+                    top + y as u32 * scale + dy,
+                    Rgb([r, g, b]),
+                );
+            }
+        }
+    }
+}
+
+fn draw_border(image: &mut RgbImage, (left, top): (u32, u32), size: u32) {
+    for dx in 0..size {
+        for dy in 0..size {
+            let edge = dx == 0 || dy == 0 || dx == size - 1 || dy == size - 1;
+            if edge || (dx + dy) % 6 == 0 {
+                image.put_pixel(left + dx, top + dy, SHEET_FOREGROUND);
+            }
+        }
+    }
+}
+
+fn draw_arrow(image: &mut RgbImage, direction: Direction, (left, top): (u32, u32), size: u32) {
+    // a triangle pointing right, rotated into `direction`
+    let half = size as i32 / 2;
+    for dx in 0..size as i32 {
+        for dy in 0..size as i32 {
+            let (u, v) = match direction {
+                Direction::Right => (dx, dy),
+                Direction::Left => (size as i32 - 1 - dx, dy),
+                Direction::Down => (dy, dx),
+                Direction::Up => (size as i32 - 1 - dy, dx),
+            };
+            // `u` runs along the arrow, `v` across it
+            let margin = size as i32 / 4;
+            if u >= margin
+                && u < size as i32 - margin
+                && (v - half).abs() <= (size as i32 - margin - u) / 2
+            {
+                image.put_pixel(left + dx as u32, top + dy as u32, SHEET_FOREGROUND);
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Samples painted in memory for the tests, so they don't depend on image files.
+
+use ndarray::Array2;
+
+use super::{Tileset, WfcColor, TILE_SIZE};
+use crate::wfc::topology::{Square, Topology};
+
+// dark pixels of every pattern, indexed `[y][x]`, the last three look different when
+// mirrored
+const PATTERNS: [[[bool; TILE_SIZE]; TILE_SIZE]; 5] = [
+    [[false; TILE_SIZE]; TILE_SIZE],
+    [[true; TILE_SIZE]; TILE_SIZE],
+    [
+        [true, false, false],
+        [true, true, false],
+        [true, false, false],
+    ],
+    [
+        [false, false, false],
+        [false, true, false],
+        [true, true, true],
+    ],
+    [
+        [true, true, false],
+        [false, false, false],
+        [false, false, false],
+    ],
+];
+
+/// Plain light tile.
+pub(crate) const FLOOR: usize = 0;
+/// Plain dark tile.
+pub(crate) const WALL: usize = 1;
+
+/// Paints the patterns `ids`, indexed `[x, y]`, into a sample.
+pub(crate) fn paint(ids: &Array2<usize>) -> Array2<WfcColor> {
+    let (width, height) = ids.dim();
+    Array2::from_shape_fn((width * TILE_SIZE, height * TILE_SIZE), |(x, y)| {
+        let pattern = PATTERNS[ids[[x / TILE_SIZE, y / TILE_SIZE]]];
+        if pattern[y % TILE_SIZE][x % TILE_SIZE] {
+            WfcColor::new(40, 40, 40)
+        } else {
+            WfcColor::new(230, 230, 230)
+        }
+    })
+}
+
+/// `image` next to its mirror image and above the half turned whole, so every tile
+/// of it has its mirrored counterparts in the sample.
+pub(crate) fn mirrored(image: &Array2<WfcColor>) -> Array2<WfcColor> {
+    let (width, height) = image.dim();
+    Array2::from_shape_fn((2 * width, 2 * height), |(x, y)| {
+        let x = if x < width { x } else { 2 * width - 1 - x };
+        let y = if y < height { y } else { 2 * height - 1 - y };
+        image[[x, y]]
+    })
+}
+
+/// Learns a tileset from `image` with `topology`.
+pub(crate) fn learn<T: Topology>(image: &Array2<WfcColor>, topology: &T) -> Tileset {
+    let mut tileset = Tileset::empty();
+    tileset
+        .add_sample(image, 1.0, topology)
+        .expect("fixtures are cut into whole tiles");
+    tileset
+}
+
+/// Rows of patterns, turned into `[x, y]` ids.
+pub(crate) fn ids(rows: &[&[usize]]) -> Array2<usize> {
+    Array2::from_shape_fn((rows[0].len(), rows.len()), |(x, y)| rows[y][x])
+}
+
+/// Tiles of all patterns with some variety in how they are arranged.
+pub(crate) fn sample() -> Array2<WfcColor> {
+    mirrored(&paint(&ids(&[
+        &[0, 0, 2, 1],
+        &[0, 3, 1, 1],
+        &[4, 0, 0, 2],
+        &[0, 1, 3, 0],
+    ])))
+}
+
+/// The tileset of [`sample`] on the square grid.
+pub(crate) fn tileset() -> Tileset {
+    learn(&sample(), &Square)
+}
+
+/// [`FLOOR`] and [`WALL`], where each may be next to the other and to itself on every side.
+pub(crate) fn floors_and_walls() -> Tileset {
+    let tileset = learn(
+        &paint(&ids(&[
+            &[0, 0, 1, 1, 0],
+            &[0, 1, 1, 0, 1],
+            &[1, 1, 0, 0, 1],
+            &[1, 0, 0, 1, 0],
+        ])),
+        &Square,
+    );
+    assert_eq!(
+        tileset.len(),
+        2,
+        "the floor and the wall are the only tiles"
+    );
+    tileset
+}
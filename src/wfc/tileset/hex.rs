@@ -0,0 +1,146 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use ndarray::Array2;
+use serde::Deserialize;
+
+use super::{read_png, Tile, Tileset, TilesetError, WfcColor, TILE_SIZE};
+use crate::wfc::hex::{Hex, HexDirection};
+
+// A hex map given as tile ids at axial coordinates, with one entry per tile id that is
+// either a color or a tile image relative to the map:
+//
+// {
+//     "tiles": ["#3c8c3c", "#2850b4", "mountain.png"],
+//     "cells": [[0, 0, 0], [1, 0, 1], [0, 1, 2]]
+// }
+//
+// Every cell is `[q, r, tile id]`, the map doesn't need to be a rectangle. Cells
+// missing around a cell count as the border of the map.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HexMap {
+    tiles: Vec<String>,
+    cells: Vec<(isize, isize, usize)>,
+}
+
+impl Tileset {
+    /// Learns a tileset for the [`Hex`] topology from a JSON map of tile ids at axial
+    /// coordinates.
+    ///
+    /// Every tile id in the map refers to an entry of `tiles`, a `"#rrggbb"` color or
+    /// the path of a [`TILE_SIZE`]×[`TILE_SIZE`] png relative to the map. The weight of
+    /// a tile is the number of cells it appears in.
+    pub fn from_hex_map(path: &str) -> Result<Self, TilesetError> {
+        let map: HexMap = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if map.tiles.is_empty() {
+            return Err(TilesetError::Invalid(format!("{path} has no tiles")));
+        }
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+
+        let mut tiles: Vec<Tile> = Vec::new();
+        for entry in &map.tiles {
+            let image: Array2<WfcColor> = match entry.strip_prefix('#') {
+                Some(_) => Array2::from_elem(
+                    (TILE_SIZE, TILE_SIZE),
+                    entry.parse().map_err(TilesetError::Invalid)?,
+                ),
+                None => read_png(&dir.join(entry).to_string_lossy())?,
+            };
+            if image.shape() != [TILE_SIZE, TILE_SIZE] {
+                return Err(TilesetError::Invalid(format!(
+                    "image {entry:?} must be {TILE_SIZE}x{TILE_SIZE} pixels"
+                )));
+            }
+            tiles.push(Tile {
+                image,
+                weight: 0.0,
+                neighbours: vec![Vec::new(); HexDirection::ALL.len()],
+            });
+        }
+
+        let mut cells: HashMap<(isize, isize), usize> = HashMap::new();
+        for &(q, r, id) in &map.cells {
+            let Some(tile) = tiles.get_mut(id) else {
+                return Err(TilesetError::Invalid(format!(
+                    "cell ({q}, {r}) has tile {id}, there are only {} tiles",
+                    map.tiles.len()
+                )));
+            };
+            if cells.insert((q, r), id).is_some() {
+                return Err(TilesetError::Invalid(format!(
+                    "cell ({q}, {r}) is listed twice"
+                )));
+            }
+            tile.weight += 1.0;
+        }
+        if let Some(id) = tiles.iter().position(|tile| tile.weight == 0.0) {
+            return Err(TilesetError::Invalid(format!(
+                "tile {id} doesn't appear in the map"
+            )));
+        }
+
+        // computing neighbours, `None` where the map ends in that direction
+        for &(q, r, id) in &map.cells {
+            for direction in HexDirection::ALL {
+                let (dq, dr) = direction.axial_step();
+                let neighbour_id = cells.get(&(q + dq, r + dr)).copied();
+                tiles[id].add_neighbour(direction.index(), neighbour_id);
+            }
+        }
+
+        Ok(Tileset { tiles })
+    }
+
+    /// Learns a tileset for the [`Hex`] topology from a png cut into tiles like a
+    /// square sample, with the tiles taken as the cells of an offset hex map where odd
+    /// rows are shifted half a cell to the east, see [`HexLayout::Offset`].
+    ///
+    /// [`HexLayout::Offset`]: crate::wfc::hex::HexLayout::Offset
+    pub fn from_hex_png(path: &str) -> Result<Self, TilesetError> {
+        Tileset::from_pngs_in(&[(path, 1.0)], &Hex::offset())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    // writes `json` to a map file of its own and learns it
+    fn learn(name: &str, json: &str) -> Result<Tileset, TilesetError> {
+        let path = env::temp_dir().join(format!("tiling-wfc-{}-{name}.json", std::process::id()));
+        fs::write(&path, json).unwrap();
+        let res = Tileset::from_hex_map(&path.to_string_lossy());
+        fs::remove_file(&path).unwrap();
+        res
+    }
+
+    #[test]
+    fn maps_without_tiles_are_invalid() {
+        let res = learn("empty", r#"{"tiles": [], "cells": []}"#);
+        assert!(matches!(res, Err(TilesetError::Invalid(_))));
+    }
+
+    #[test]
+    fn neighbours_follow_the_axial_steps() {
+        // tile 1 east of tile 0, tile 2 south east of tile 0
+        let tileset = learn(
+            "triangle",
+            r##"{"tiles": ["#000000", "#ffffff", "#ff0000"], "cells": [[0, 0, 0], [1, 0, 1], [0, 1, 2]]}"##,
+        )
+        .unwrap();
+        assert_eq!(tileset.directions(), HexDirection::ALL.len());
+        let neighbours = |id: usize, direction: HexDirection| {
+            tileset[id].get_neighbours_in(direction.index()).clone()
+        };
+        assert_eq!(neighbours(0, HexDirection::East), [Some(1)]);
+        assert_eq!(neighbours(1, HexDirection::West), [Some(0)]);
+        assert_eq!(neighbours(0, HexDirection::SouthEast), [Some(2)]);
+        assert_eq!(neighbours(2, HexDirection::NorthWest), [Some(0)]);
+        // tile 2 is south west of tile 1
+        assert_eq!(neighbours(1, HexDirection::SouthWest), [Some(2)]);
+        assert_eq!(neighbours(0, HexDirection::West), [None]);
+    }
+}
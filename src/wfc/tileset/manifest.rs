@@ -0,0 +1,406 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use ndarray::Array2;
+use serde::Deserialize;
+
+use super::{read_png, Direction, Tile, Tileset, TilesetError, WfcColor, TILE_SIZE};
+
+// A tileset described by separate tile images and explicit neighbour rules, in the
+// spirit of the simple tiled model of the original WFC:
+//
+// {
+//     "tiles": [
+//         { "name": "grass", "image": "grass.png", "weight": 4.0 },
+//         { "name": "road", "image": "road.png", "symmetry": "I" },
+//         { "name": "turn", "image": "turn.png", "symmetry": "L", "border": false }
+//     ],
+//     "neighbors": [
+//         { "left": "grass", "right": "road" },
+//         { "up": "road 1", "down": "turn" }
+//     ]
+// }
+//
+// Every tile is expanded into its distinct rotations and reflections according to its
+// symmetry class. `name k` refers to the k-th variant, `name` to the tile as drawn.
+// Rules are stated for one orientation and hold for all rotated and reflected
+// versions of the pair as well.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    tiles: Vec<TileEntry>,
+    #[serde(default)]
+    neighbors: Vec<NeighborRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TileEntry {
+    name: String,
+    // relative to the manifest
+    image: String,
+    #[serde(default = "default_weight")]
+    weight: f64,
+    #[serde(default)]
+    symmetry: Symmetry,
+    // whether the tile may touch the border of the output
+    #[serde(default = "default_border")]
+    border: bool,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+fn default_border() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NeighborRule {
+    Horizontal { left: String, right: String },
+    Vertical { up: String, down: String },
+}
+
+/// Symmetry class of a tile as drawn, using the letters of the original WFC.
+#[derive(Deserialize, Default, Clone, Copy)]
+enum Symmetry {
+    /// symmetric under every rotation and reflection, 1 variant
+    #[default]
+    X,
+    /// straight line like `│`, 2 variants
+    I,
+    /// diagonal line like `\`, 2 variants
+    #[serde(rename = "\\", alias = "/")]
+    Diagonal,
+    /// symmetric about the vertical axis like `┬`, 4 variants
+    T,
+    /// corner like `└`, symmetric about the anti-diagonal, 4 variants
+    L,
+    /// no symmetry at all, 8 variants
+    F,
+}
+
+/// Element of the symmetry group of the square, as a matrix acting on `(x, y)`
+/// with `x` going right and `y` going down.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Transform([[i32; 2]; 2]);
+
+impl Transform {
+    const IDENTITY: Transform = Transform([[1, 0], [0, 1]]);
+    // a quarter turn clockwise
+    const ROTATE: Transform = Transform([[0, -1], [1, 0]]);
+    const HALF_TURN: Transform = Transform([[-1, 0], [0, -1]]);
+    // mirrored left to right
+    const MIRROR: Transform = Transform([[-1, 0], [0, 1]]);
+    const FLIP: Transform = Transform([[1, 0], [0, -1]]);
+    // mirrored about `\` and `/`
+    const DIAGONAL: Transform = Transform([[0, 1], [1, 0]]);
+    const ANTI_DIAGONAL: Transform = Transform([[0, -1], [-1, 0]]);
+
+    /// All eight transforms, rotations first, then the mirrored rotations.
+    fn all() -> Vec<Transform> {
+        let mut res = Vec::with_capacity(8);
+        for reflection in [Transform::IDENTITY, Transform::MIRROR] {
+            let mut transform = reflection;
+            for _ in 0..4 {
+                res.push(transform);
+                transform = Transform::ROTATE.after(transform);
+            }
+        }
+        res
+    }
+    fn index(self) -> usize {
+        Transform::all().iter().position(|&t| t == self).unwrap()
+    }
+    /// Returns the transform applying `inner` first and `self` second.
+    fn after(self, inner: Transform) -> Transform {
+        let [[a, b], [c, d]] = self.0;
+        let [[e, f], [g, h]] = inner.0;
+        Transform([
+            [a * e + b * g, a * f + b * h],
+            [c * e + d * g, c * f + d * h],
+        ])
+    }
+    fn apply(self, (x, y): (i32, i32)) -> (i32, i32) {
+        let [[a, b], [c, d]] = self.0;
+        (a * x + b * y, c * x + d * y)
+    }
+    fn apply_to_image(self, image: &Array2<WfcColor>) -> Array2<WfcColor> {
+        // work on doubled coordinates centered on the middle of the tile
+        let size = TILE_SIZE as i32;
+        let mut res = image.clone();
+        for ((x, y), &color) in image.indexed_iter() {
+            let centered = (2 * x as i32 - (size - 1), 2 * y as i32 - (size - 1));
+            let (u, v) = self.apply(centered);
+            res[[((u + size - 1) / 2) as usize, ((v + size - 1) / 2) as usize]] = color;
+        }
+        res
+    }
+}
+
+impl Symmetry {
+    fn stabilizer(self) -> Vec<Transform> {
+        use Transform as T;
+        match self {
+            Symmetry::X => T::all(),
+            Symmetry::I => vec![T::IDENTITY, T::HALF_TURN, T::MIRROR, T::FLIP],
+            Symmetry::Diagonal => vec![T::IDENTITY, T::HALF_TURN, T::DIAGONAL, T::ANTI_DIAGONAL],
+            Symmetry::T => vec![T::IDENTITY, T::MIRROR],
+            Symmetry::L => vec![T::IDENTITY, T::ANTI_DIAGONAL],
+            Symmetry::F => vec![T::IDENTITY],
+        }
+    }
+    /// Index of the first transform giving the same variant as `transform`.
+    fn canonical(self, transform: Transform) -> usize {
+        self.stabilizer()
+            .into_iter()
+            .map(|s| transform.after(s).index())
+            .min()
+            .unwrap()
+    }
+}
+
+struct BaseTile {
+    symmetry: Symmetry,
+    // tile id of every variant, by canonical transform index
+    ids: HashMap<usize, usize>,
+    // transforms producing variant `k`, in order
+    variants: Vec<Transform>,
+}
+
+impl Tileset {
+    /// Builds a tileset from a JSON manifest listing tile images, their weights,
+    /// symmetry classes and explicit neighbour rules.
+    ///
+    /// Every tile is expanded into its rotated and reflected variants according to
+    /// its symmetry class (`X`, `I`, `\`, `T`, `L` or `F`), and every neighbour rule
+    /// is applied to all rotated and reflected versions of the pair.
+    pub fn from_manifest(path: &str) -> Result<Self, TilesetError> {
+        let manifest: Manifest = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if manifest.tiles.is_empty() {
+            return Err(TilesetError::Invalid(
+                "manifest doesn't list any tiles".to_string(),
+            ));
+        }
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+
+        let mut tiles: Vec<Tile> = Vec::new();
+        let mut bases: HashMap<String, BaseTile> = HashMap::new();
+        for entry in manifest.tiles {
+            if bases.contains_key(&entry.name) {
+                return Err(TilesetError::Invalid(format!(
+                    "tile {:?} is listed twice",
+                    entry.name
+                )));
+            }
+            if !(entry.weight.is_finite() && entry.weight > 0.0) {
+                return Err(TilesetError::Invalid(format!(
+                    "weight of tile {:?} must be positive",
+                    entry.name
+                )));
+            }
+            let image_path = dir.join(&entry.image);
+            let image: Array2<WfcColor> = read_png(&image_path.to_string_lossy())?;
+            if image.shape() != [TILE_SIZE, TILE_SIZE] {
+                return Err(TilesetError::Invalid(format!(
+                    "image of tile {:?} must be {TILE_SIZE}x{TILE_SIZE} pixels",
+                    entry.name
+                )));
+            }
+
+            let mut base = BaseTile {
+                symmetry: entry.symmetry,
+                ids: HashMap::new(),
+                variants: Vec::new(),
+            };
+            for transform in Transform::all() {
+                let canonical = entry.symmetry.canonical(transform);
+                if base.ids.contains_key(&canonical) {
+                    continue;
+                }
+                base.ids.insert(canonical, tiles.len());
+                base.variants.push(transform);
+
+                let border = if entry.border { vec![None] } else { Vec::new() };
+                tiles.push(Tile {
+                    image: transform.apply_to_image(&image),
+                    weight: entry.weight,
+                    neighbours: vec![border; Direction::ALL.len()],
+                });
+            }
+            bases.insert(entry.name, base);
+        }
+
+        for rule in manifest.neighbors {
+            // `second` may be placed at `direction` from `first`
+            let (first, second, direction) = match &rule {
+                NeighborRule::Horizontal { left, right } => (left, right, (1, 0)),
+                NeighborRule::Vertical { up, down } => (up, down, (0, 1)),
+            };
+            let (first_base, first_transform) = lookup(&bases, first)?;
+            let (second_base, second_transform) = lookup(&bases, second)?;
+
+            for transform in Transform::all() {
+                let first_id = first_base.ids[&first_base
+                    .symmetry
+                    .canonical(transform.after(first_transform))];
+                let second_id = second_base.ids[&second_base
+                    .symmetry
+                    .canonical(transform.after(second_transform))];
+                let (dx, dy) = transform.apply(direction);
+                add_neighbour(&mut tiles[first_id], (dx, dy), second_id);
+                add_neighbour(&mut tiles[second_id], (-dx, -dy), first_id);
+            }
+        }
+
+        Ok(Tileset { tiles })
+    }
+}
+
+/// Resolves `name` or `name k` to its base tile and the transform of variant `k`.
+fn lookup<'a>(
+    bases: &'a HashMap<String, BaseTile>,
+    name: &str,
+) -> Result<(&'a BaseTile, Transform), TilesetError> {
+    let (base_name, variant) = match name.rsplit_once(' ') {
+        Some((base_name, k)) if k.parse::<usize>().is_ok() => (base_name, k.parse().unwrap()),
+        _ => (name, 0),
+    };
+    let base = bases
+        .get(base_name)
+        .ok_or_else(|| TilesetError::Invalid(format!("unknown tile {base_name:?} in rule")))?;
+    match base.variants.get(variant) {
+        Some(&transform) => Ok((base, transform)),
+        None => Err(TilesetError::Invalid(format!(
+            "tile {base_name:?} only has {} variants",
+            base.variants.len()
+        ))),
+    }
+}
+
+fn add_neighbour(tile: &mut Tile, direction: (i32, i32), id: usize) {
+    match direction {
+        (-1, 0) => tile.add_left(Some(id)),
+        (1, 0) => tile.add_right(Some(id)),
+        (0, -1) => tile.add_up(Some(id)),
+        (0, 1) => tile.add_down(Some(id)),
+        _ => unreachable!("transforms keep unit directions"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    // dark pixels of a tile, rows from the top
+    fn pixels(rows: [&str; TILE_SIZE]) -> Array2<WfcColor> {
+        Array2::from_shape_fn((TILE_SIZE, TILE_SIZE), |(x, y)| {
+            if rows[y].as_bytes()[x] == b'#' {
+                WfcColor::new(0, 0, 0)
+            } else {
+                WfcColor::new(255, 255, 255)
+            }
+        })
+    }
+
+    const BLANK: [&str; TILE_SIZE] = ["...", "...", "..."];
+    // `│`, symmetry I
+    const LINE: [&str; TILE_SIZE] = [".#.", ".#.", ".#."];
+    // `└`, symmetry L
+    const CORNER: [&str; TILE_SIZE] = [".#.", ".##", "..."];
+
+    // writes the tile images and `manifest` to a directory of their own and loads it
+    fn load(name: &str, manifest: &str) -> Result<Tileset, TilesetError> {
+        let dir = env::temp_dir().join(format!("tiling-wfc-{}-{name}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, rows) in [
+            ("blank.png", BLANK),
+            ("line.png", LINE),
+            ("corner.png", CORNER),
+        ] {
+            let image = pixels(rows);
+            RgbImage::from_fn(TILE_SIZE as u32, TILE_SIZE as u32, |x, y| {
+                let (r, g, b) = image[[x as usize, y as usize]].get();
+                Rgb([r, g, b])
+            })
+            .save(dir.join(file))
+            .unwrap();
+        }
+        let path = dir.join("manifest.json");
+        fs::write(&path, manifest).unwrap();
+        let res = Tileset::from_manifest(&path.to_string_lossy());
+        fs::remove_dir_all(&dir).unwrap();
+        res
+    }
+
+    fn sorted(neighbours: &[Option<usize>]) -> Vec<Option<usize>> {
+        let mut neighbours = neighbours.to_vec();
+        neighbours.sort();
+        neighbours
+    }
+
+    #[test]
+    fn tiles_are_expanded_by_their_symmetry() {
+        let tileset = load(
+            "symmetry",
+            r#"{
+                "tiles": [
+                    { "name": "blank", "image": "blank.png" },
+                    { "name": "line", "image": "line.png", "symmetry": "I" },
+                    { "name": "corner", "image": "corner.png", "symmetry": "L" }
+                ],
+                "neighbors": [{ "left": "corner", "right": "line 1" }]
+            }"#,
+        )
+        .unwrap();
+        // the variants are the rotations, each a quarter turn clockwise of the one before
+        let variants = [
+            BLANK,
+            LINE,
+            ["...", "###", "..."],
+            CORNER,
+            ["...", ".##", ".#."],
+            ["...", "##.", ".#."],
+            [".#.", "##.", "..."],
+        ];
+        assert_eq!(tileset.len(), variants.len());
+        for (id, rows) in variants.into_iter().enumerate() {
+            assert_eq!(tileset[id].get_image(), pixels(rows), "image of tile {id}");
+        }
+
+        // the rule holds for every rotated and mirrored version of the pair
+        let (line, corner) = (1, 3);
+        let neighbours =
+            |id: usize, direction: Direction| sorted(tileset[id].get_neighbours(direction));
+        assert_eq!(neighbours(corner, Direction::Right), [None, Some(line + 1)]);
+        assert_eq!(neighbours(corner, Direction::Up), [None, Some(line)]);
+        assert_eq!(neighbours(corner, Direction::Left), [None]);
+        assert_eq!(
+            neighbours(line, Direction::Down),
+            [None, Some(corner), Some(corner + 3)]
+        );
+        assert_eq!(
+            neighbours(line + 1, Direction::Left),
+            [None, Some(corner), Some(corner + 1)]
+        );
+        assert_eq!(neighbours(0, Direction::Right), [None]);
+    }
+
+    #[test]
+    fn rules_with_unknown_tiles_are_invalid() {
+        let res = load(
+            "unknown",
+            r#"{
+                "tiles": [{ "name": "line", "image": "line.png", "symmetry": "I" }],
+                "neighbors": [{ "left": "line", "right": "road" }]
+            }"#,
+        );
+        assert!(matches!(res, Err(TilesetError::Invalid(_))));
+    }
+}
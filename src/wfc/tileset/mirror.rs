@@ -0,0 +1,26 @@
+use ndarray::Array2;
+
+use super::{Tileset, WfcColor, TILE_SIZE};
+use crate::wfc::symmetry::Mirror;
+
+impl Tileset {
+    /// Returns for every tile the id of the tile whose image is its image mirrored by
+    /// `mirror`, or `None` if the tileset has no such tile.
+    ///
+    /// Symmetric outputs can only use tiles that have a counterpart, see
+    /// [`WaveFunction::require_symmetry`].
+    ///
+    /// [`WaveFunction::require_symmetry`]: crate::wfc::WaveFunction::require_symmetry
+    pub fn counterparts(&self, mirror: Mirror) -> Vec<Option<usize>> {
+        self.tiles
+            .iter()
+            .map(|tile| {
+                let mirrored: Array2<WfcColor> =
+                    Array2::from_shape_fn((TILE_SIZE, TILE_SIZE), |coords| {
+                        tile.image[mirror.apply(coords, [TILE_SIZE, TILE_SIZE])]
+                    });
+                self.get_id(mirrored.view())
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,297 @@
+use std::{fmt, fs, io};
+
+use bincode::Options;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use super::{Direction, Tile, Tileset, WfcColor, TILE_SIZE};
+
+/// Error returned when building, saving or loading a [`Tileset`] fails.
+#[derive(Debug)]
+pub enum TilesetError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+    Image(image::ImageError),
+    /// The data was read but doesn't describe a valid tileset.
+    Invalid(String),
+}
+
+impl fmt::Display for TilesetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TilesetError::Io(err) => write!(f, "not able to access tileset file: {err}"),
+            TilesetError::Json(err) => write!(f, "not able to parse tileset json: {err}"),
+            TilesetError::Binary(err) => write!(f, "not able to parse binary tileset: {err}"),
+            TilesetError::Image(err) => write!(f, "not able to decode image: {err}"),
+            TilesetError::Invalid(reason) => write!(f, "invalid tileset: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for TilesetError {}
+
+impl From<io::Error> for TilesetError {
+    fn from(err: io::Error) -> Self {
+        TilesetError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for TilesetError {
+    fn from(err: serde_json::Error) -> Self {
+        TilesetError::Json(err)
+    }
+}
+
+impl From<bincode::Error> for TilesetError {
+    fn from(err: bincode::Error) -> Self {
+        TilesetError::Binary(err)
+    }
+}
+
+impl From<image::ImageError> for TilesetError {
+    fn from(err: image::ImageError) -> Self {
+        TilesetError::Image(err)
+    }
+}
+
+// on-disk layout, kept separate from `Tile` so the files stay readable and editable by hand
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TilesetData {
+    tile_size: usize,
+    tiles: Vec<TileData>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TileData {
+    // indexed `[x][y]` like `Tile::image`
+    pixels: Vec<Vec<WfcColor>>,
+    weight: f64,
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    border: Borders,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Borders {
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+}
+
+impl From<&Tile> for TileData {
+    fn from(tile: &Tile) -> Self {
+        fn ids(neighbours: &[Option<usize>]) -> Vec<usize> {
+            neighbours.iter().flatten().copied().collect()
+        }
+
+        Self {
+            pixels: tile.image.outer_iter().map(|row| row.to_vec()).collect(),
+            weight: tile.weight,
+            left: ids(tile.get_left()),
+            right: ids(tile.get_right()),
+            up: ids(tile.get_up()),
+            down: ids(tile.get_down()),
+            border: Borders {
+                left: tile.is_left_border(),
+                right: tile.is_right_border(),
+                up: tile.is_upper_border(),
+                down: tile.is_lower_border(),
+            },
+        }
+    }
+}
+
+impl TileData {
+    fn into_tile(self, num_tiles: usize) -> Result<Tile, TilesetError> {
+        fn neighbours(
+            ids: Vec<usize>,
+            border: bool,
+            num_tiles: usize,
+        ) -> Result<Vec<Option<usize>>, TilesetError> {
+            if let Some(id) = ids.iter().find(|&&id| id >= num_tiles) {
+                return Err(TilesetError::Invalid(format!(
+                    "neighbour {id} doesn't exist, there are only {num_tiles} tiles"
+                )));
+            }
+            let mut res: Vec<Option<usize>> = ids.into_iter().map(Some).collect();
+            if border {
+                res.push(None);
+            }
+            Ok(res)
+        }
+
+        if self.pixels.len() != TILE_SIZE || self.pixels.iter().any(|row| row.len() != TILE_SIZE) {
+            return Err(TilesetError::Invalid(format!(
+                "tile pixels must be {TILE_SIZE}x{TILE_SIZE}"
+            )));
+        }
+        if !(self.weight.is_finite() && self.weight > 0.0) {
+            return Err(TilesetError::Invalid(format!(
+                "tile weight must be positive, got {}",
+                self.weight
+            )));
+        }
+
+        let image: Array2<WfcColor> =
+            Array2::from_shape_fn((TILE_SIZE, TILE_SIZE), |(x, y)| self.pixels[x][y]);
+
+        Ok(Tile {
+            image,
+            weight: self.weight,
+            // in the order of `Direction::ALL`
+            neighbours: vec![
+                neighbours(self.left, self.border.left, num_tiles)?,
+                neighbours(self.right, self.border.right, num_tiles)?,
+                neighbours(self.up, self.border.up, num_tiles)?,
+                neighbours(self.down, self.border.down, num_tiles)?,
+            ],
+        })
+    }
+}
+
+impl From<&Tileset> for TilesetData {
+    fn from(tileset: &Tileset) -> Self {
+        assert_eq!(
+            tileset.directions(),
+            Direction::ALL.len(),
+            "only tilesets with the four sides as directions can be stored"
+        );
+        Self {
+            tile_size: TILE_SIZE,
+            tiles: tileset.tiles.iter().map(TileData::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<TilesetData> for Tileset {
+    type Error = TilesetError;
+
+    fn try_from(data: TilesetData) -> Result<Self, Self::Error> {
+        if data.tile_size != TILE_SIZE {
+            return Err(TilesetError::Invalid(format!(
+                "tile size {} doesn't match the supported tile size {TILE_SIZE}",
+                data.tile_size
+            )));
+        }
+        let num_tiles = data.tiles.len();
+        let tiles = data
+            .tiles
+            .into_iter()
+            .map(|tile| tile.into_tile(num_tiles))
+            .collect::<Result<Vec<Tile>, TilesetError>>()?;
+
+        Ok(Tileset { tiles })
+    }
+}
+
+impl Tileset {
+    /// Serializes the tileset as pretty printed JSON.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tileset was learned with a topology that has other directions
+    /// than the four sides, the format only stores those.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&TilesetData::from(self))
+            .expect("tileset data is always representable as json")
+    }
+    pub fn from_json(json: &str) -> Result<Self, TilesetError> {
+        let data: TilesetData = serde_json::from_str(json)?;
+        Tileset::try_from(data)
+    }
+    /// Serializes the tileset into a compact binary format.
+    ///
+    /// # Panics
+    ///
+    /// Like [`Tileset::to_json`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::DefaultOptions::new()
+            .serialize(&TilesetData::from(self))
+            .expect("tileset data is always representable as bincode")
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TilesetError> {
+        let data: TilesetData = bincode::DefaultOptions::new().deserialize(bytes)?;
+        Tileset::try_from(data)
+    }
+    /// Writes the tileset to `path`, as JSON if the path ends in `.json` and in the
+    /// binary format otherwise.
+    pub fn save(&self, path: &str) -> Result<(), TilesetError> {
+        self.require_sides("storing")?;
+        if path.ends_with(".json") {
+            fs::write(path, self.to_json())?;
+        } else {
+            fs::write(path, self.to_bytes())?;
+        }
+        Ok(())
+    }
+    /// Reads a tileset written by [`Tileset::save`].
+    pub fn load(path: &str) -> Result<Self, TilesetError> {
+        if path.ends_with(".json") {
+            Tileset::from_json(&fs::read_to_string(path)?)
+        } else {
+            Tileset::from_bytes(&fs::read(path)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::wfc::tileset::fixtures;
+
+    // the border is stored apart from the neighbour ids, so it may come back in another place
+    fn sorted(neighbours: &[Option<usize>]) -> Vec<Option<usize>> {
+        let mut neighbours = neighbours.to_vec();
+        neighbours.sort();
+        neighbours
+    }
+
+    fn assert_same_tiles(loaded: &Tileset, tileset: &Tileset) {
+        assert_eq!(loaded.len(), tileset.len());
+        for id in 0..tileset.len() {
+            assert_eq!(loaded[id].get_image(), tileset[id].get_image());
+            assert_eq!(loaded[id].get_weight(), tileset[id].get_weight());
+            for direction in Direction::ALL {
+                assert_eq!(
+                    sorted(loaded[id].get_neighbours(direction)),
+                    sorted(tileset[id].get_neighbours(direction)),
+                    "neighbours of tile {id} on the {direction} side"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn json_and_bincode_round_trip() {
+        let tileset = fixtures::tileset();
+        for name in ["tileset.json", "tileset.bin"] {
+            let path = env::temp_dir()
+                .join(format!("tiling-wfc-{}-{name}", std::process::id()))
+                .to_string_lossy()
+                .into_owned();
+            tileset.save(&path).unwrap();
+            let loaded = Tileset::load(&path);
+            fs::remove_file(&path).unwrap();
+            assert_same_tiles(&loaded.unwrap(), &tileset);
+        }
+        assert_same_tiles(&Tileset::from_json(&tileset.to_json()).unwrap(), &tileset);
+        assert_same_tiles(&Tileset::from_bytes(&tileset.to_bytes()).unwrap(), &tileset);
+    }
+
+    #[test]
+    fn neighbours_that_do_not_exist_are_invalid() {
+        let json = fixtures::tileset()
+            .to_json()
+            .replacen("\"left\": [", "\"left\": [\n    99,", 1);
+        assert!(matches!(
+            Tileset::from_json(&json),
+            Err(TilesetError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+use super::Coordinates;
+
+/// How the cells of a grid are connected, used by [`WaveFunction`] to find the cells
+/// a change propagates to and by [`Tileset::from_pngs_in`] to find which tiles touch
+/// in a sample.
+///
+/// Directions are numbered from `0` to `directions() - 1`, a tile keeps one neighbour
+/// list per direction, see [`Tile::get_neighbours_in`]. A tileset can only be used
+/// with a topology that has the same number of directions as the one it was learned
+/// with.
+///
+/// [`WaveFunction`]: super::WaveFunction
+/// [`Tileset::from_pngs_in`]: super::tileset::Tileset::from_pngs_in
+/// [`Tile::get_neighbours_in`]: super::tileset::Tile::get_neighbours_in
+pub trait Topology {
+    /// Returns the number of directions every cell has.
+    fn directions(&self) -> usize;
+    /// Returns the cell next to `coords` in `direction` on a grid of `shape` cells, or
+    /// `None` if `coords` is on the border of the grid on that side.
+    fn neighbour(
+        &self,
+        coords: Coordinates,
+        direction: usize,
+        shape: [usize; 2],
+    ) -> Option<Coordinates>;
+    /// Returns the direction that leads from the neighbour in `direction` back to the cell.
+    fn opposite(&self, direction: usize) -> usize;
+    /// Returns a name that tells this grid apart from other topologies, checkpoints
+    /// store it to be resumed on the same grid.
+    fn name(&self) -> &'static str;
+}
+
+// steps of the four sides in the order of `Direction::ALL`
+const SIDES: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+// steps of the corners, up left, up right, down left and down right
+const CORNERS: [(isize, isize); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+fn step(
+    (x, y): Coordinates,
+    (dx, dy): (isize, isize),
+    [width, height]: [usize; 2],
+) -> Option<Coordinates> {
+    let (x, y) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+    (x < width && y < height).then_some((x, y))
+}
+
+/// The plain grid, every cell touches the cells left, right, above and below it.
+///
+/// Direction `i` is `Direction::ALL[i]`, see [`Direction::index`].
+///
+/// [`Direction::index`]: super::tileset::Direction::index
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Square;
+
+impl Topology for Square {
+    fn directions(&self) -> usize {
+        SIDES.len()
+    }
+    fn neighbour(
+        &self,
+        coords: Coordinates,
+        direction: usize,
+        shape: [usize; 2],
+    ) -> Option<Coordinates> {
+        step(coords, SIDES[direction], shape)
+    }
+    fn opposite(&self, direction: usize) -> usize {
+        // sides come in pairs, left and right, up and down
+        direction ^ 1
+    }
+    fn name(&self) -> &'static str {
+        "square"
+    }
+}
+
+/// The square grid where cells also touch the four cells on their corners.
+///
+/// Directions `0` to `3` are the ones of [`Square`], followed by up left, up right,
+/// down left and down right.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Diagonals;
+
+impl Topology for Diagonals {
+    fn directions(&self) -> usize {
+        SIDES.len() + CORNERS.len()
+    }
+    fn neighbour(
+        &self,
+        coords: Coordinates,
+        direction: usize,
+        shape: [usize; 2],
+    ) -> Option<Coordinates> {
+        match direction.checked_sub(SIDES.len()) {
+            None => step(coords, SIDES[direction], shape),
+            Some(corner) => step(coords, CORNERS[corner], shape),
+        }
+    }
+    fn opposite(&self, direction: usize) -> usize {
+        match direction.checked_sub(SIDES.len()) {
+            None => direction ^ 1,
+            // up left and down right, up right and down left
+            Some(corner) => SIDES.len() + 3 - corner,
+        }
+    }
+    fn name(&self) -> &'static str {
+        "diagonals"
+    }
+}
+
+/// The square grid wrapped around at its borders, the left column touches the right
+/// one and the top row the bottom one, so outputs tile seamlessly.
+///
+/// There is no border, so tiles learned with this topology have no `None` neighbours
+/// and any tile may end up on the edge of the output. Directions are the ones of
+/// [`Square`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Periodic;
+
+impl Topology for Periodic {
+    fn directions(&self) -> usize {
+        SIDES.len()
+    }
+    fn neighbour(
+        &self,
+        (x, y): Coordinates,
+        direction: usize,
+        [width, height]: [usize; 2],
+    ) -> Option<Coordinates> {
+        let (dx, dy) = SIDES[direction];
+        Some((
+            (x as isize + dx).rem_euclid(width as isize) as usize,
+            (y as isize + dy).rem_euclid(height as isize) as usize,
+        ))
+    }
+    fn opposite(&self, direction: usize) -> usize {
+        direction ^ 1
+    }
+    fn name(&self) -> &'static str {
+        "periodic"
+    }
+}
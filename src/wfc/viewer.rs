@@ -0,0 +1,101 @@
+use ndarray::Array2;
+use raylib::prelude::*;
+
+use super::tileset::TILE_SIZE;
+use super::{Coordinates, WaveFunction};
+
+impl WaveFunction {
+    /// Draws the collapsed cells of the wave function onto `canvas` and presents it.
+    pub fn show(&self, rl: &mut RaylibHandle, thread: &RaylibThread, canvas: &mut Canvas) {
+        // only cells whose collapsed tile differs from what the canvas holds get uploaded
+        for x in 0..self.shape[0] {
+            for y in 0..self.shape[1] {
+                let tile_opt = self.get_tile_id((x, y));
+                if canvas.drawn[[x, y]] == tile_opt {
+                    continue;
+                }
+
+                // the texture expects the pixels row by row
+                let mut pixels: Vec<u8> = Vec::with_capacity(TILE_SIZE * TILE_SIZE * 4);
+                for tile_y in 0..TILE_SIZE {
+                    for tile_x in 0..TILE_SIZE {
+                        let (r, g, b) = match tile_opt {
+                            Some(tile_id) => {
+                                self.tileset[tile_id].get_image()[[tile_x, tile_y]].get()
+                            }
+                            None => (Color::GRAY.r, Color::GRAY.g, Color::GRAY.b),
+                        };
+                        pixels.extend_from_slice(&[r, g, b, 255]);
+                    }
+                }
+                canvas.update_cell((x, y), &pixels);
+                canvas.drawn[[x, y]] = tile_opt;
+            }
+        }
+
+        let mut draw_handle = rl.begin_drawing(thread);
+
+        draw_handle.clear_background(Color::GRAY);
+        draw_handle.draw_texture_ex(
+            &canvas.texture,
+            Vector2::zero(),
+            0.0,
+            canvas.scale as f32,
+            Color::WHITE,
+        );
+    }
+}
+
+/// Texture holding the rendered output at one pixel per tile pixel.
+///
+/// `WaveFunction::show` keeps track of which tile every cell was drawn with and
+/// only uploads the cells that changed since the last frame.
+pub struct Canvas {
+    texture: Texture2D,
+    drawn: Array2<Option<usize>>,
+    scale: usize,
+}
+
+impl Canvas {
+    /// Creates a canvas for wave functions of `shape` cells, drawn `scale` times enlarged.
+    pub fn new(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        (shape_x, shape_y): (usize, usize),
+        scale: usize,
+    ) -> Self {
+        let image = Image::gen_image_color(
+            (shape_x * TILE_SIZE) as i32,
+            (shape_y * TILE_SIZE) as i32,
+            Color::GRAY,
+        );
+        let texture = match rl.load_texture_from_image(thread, &image) {
+            Ok(texture) => texture,
+            Err(_) => panic!("not able to create canvas texture"),
+        };
+
+        Self {
+            texture,
+            drawn: Array2::from_elem((shape_x, shape_y), None),
+            scale,
+        }
+    }
+
+    fn update_cell(&mut self, (x, y): Coordinates, pixels: &[u8]) {
+        assert_eq!(pixels.len(), TILE_SIZE * TILE_SIZE * 4);
+        let rec = Rectangle::new(
+            (x * TILE_SIZE) as f32,
+            (y * TILE_SIZE) as f32,
+            TILE_SIZE as f32,
+            TILE_SIZE as f32,
+        );
+        // SAFETY: `rec` lies inside the texture and `pixels` holds exactly one RGBA tile
+        unsafe {
+            ffi::UpdateTextureRec(
+                *self.texture.as_ref(),
+                rec.into(),
+                pixels.as_ptr() as *const std::os::raw::c_void,
+            );
+        }
+    }
+}
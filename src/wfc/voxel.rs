@@ -0,0 +1,262 @@
+use std::sync::Arc;
+
+use ndarray::Array3;
+use rand::Rng;
+
+use super::{
+    limits::{GaveUp, Limits},
+    observer::Observer,
+    stats::SolveStats,
+    tileset::{Tile, Tileset},
+    topology::Topology,
+    Contradiction, Coordinates, WaveFunction,
+};
+
+pub mod export;
+pub mod tileset;
+use tileset::{VoxelDirection, VoxelTileset};
+
+/// `(x, y, z)` position of a cell in the voxel output grid, `z` counts layers from
+/// the bottom up.
+pub type VoxelCoordinates = (usize, usize, usize);
+
+/// The layers of a voxel grid stacked below each other in the `[x, y]` grid of a
+/// [`WaveFunction`], layer `z` takes up the rows `z * height..(z + 1) * height`.
+///
+/// Every cell has the six neighbours of [`VoxelDirection::ALL`], the ones above and
+/// below are in the same column of the next and previous layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layers {
+    /// Number of layers the grid is split into.
+    pub depth: usize,
+}
+
+impl Layers {
+    /// Returns the cell of `coords` in the grid of a wave function of `shape` cells.
+    pub fn cell(&self, (x, y, z): VoxelCoordinates, shape: [usize; 2]) -> Coordinates {
+        (x, z * (shape[1] / self.depth) + y)
+    }
+    /// Returns the voxel coordinates of `cell` in the grid of a wave function of
+    /// `shape` cells, the reverse of [`Layers::cell`].
+    pub fn voxel(&self, (x, y): Coordinates, shape: [usize; 2]) -> VoxelCoordinates {
+        let height = shape[1] / self.depth;
+        (x, y % height, y / height)
+    }
+}
+
+impl Topology for Layers {
+    fn directions(&self) -> usize {
+        VoxelDirection::ALL.len()
+    }
+    fn neighbour(
+        &self,
+        coords: Coordinates,
+        direction: usize,
+        shape: [usize; 2],
+    ) -> Option<Coordinates> {
+        let (x, y, z) = self.voxel(coords, shape);
+        let (dx, dy, dz) = VoxelDirection::ALL[direction].offset();
+        let (x, y, z) = (
+            x.checked_add_signed(dx)?,
+            y.checked_add_signed(dy)?,
+            z.checked_add_signed(dz)?,
+        );
+        let inside = x < shape[0] && y < shape[1] / self.depth && z < self.depth;
+        inside.then(|| self.cell((x, y, z), shape))
+    }
+    fn opposite(&self, direction: usize) -> usize {
+        // directions come in pairs, left and right, up and down, below and above
+        direction ^ 1
+    }
+    fn name(&self) -> &'static str {
+        "layers"
+    }
+}
+
+/// The 3D counterpart of [`WaveFunction`], a grid of cells that each hold the set of
+/// [`VoxelTileset`] tiles still possible there.
+///
+/// Cells are connected to their six neighbours, the layers above and below included.
+/// Solving is done by a [`WaveFunction`] over the [`Layers`] of the grid, so observers
+/// see the cells as laid out there.
+pub struct VoxelWaveFunction {
+    tileset: Arc<VoxelTileset>,
+    wave_function: WaveFunction<Layers>,
+    shape: [usize; 3],
+}
+
+impl VoxelWaveFunction {
+    /// Creates an unobserved wave function of `out_shape` cells over `tileset`.
+    ///
+    /// Like in 2D, cells on the border only allow tiles that were seen on the
+    /// matching border of the sample, including its bottom and top layer.
+    pub fn new(out_shape: (usize, usize, usize), tileset: impl Into<Arc<VoxelTileset>>) -> Self {
+        let tileset: Arc<VoxelTileset> = tileset.into();
+        let (shape_x, shape_y, shape_z) = out_shape;
+        let wave_function = WaveFunction::with_topology(
+            (shape_x, shape_y * shape_z),
+            tileset.flattened(),
+            Layers { depth: shape_z },
+        );
+
+        Self {
+            tileset,
+            wave_function,
+            shape: [shape_x, shape_y, shape_z],
+        }
+    }
+    /// Returns `true` once every cell is collapsed to a single tile.
+    pub fn done(&self) -> bool {
+        self.wave_function.done()
+    }
+    /// Returns the `[width, height, depth]` of the output grid.
+    pub fn shape(&self) -> [usize; 3] {
+        self.shape
+    }
+    pub fn get_tileset(&self) -> &VoxelTileset {
+        &self.tileset
+    }
+    /// Returns the tileset as shared between wave functions, cloning it is cheap.
+    pub fn shared_tileset(&self) -> &Arc<VoxelTileset> {
+        &self.tileset
+    }
+    /// Returns the wave function over the [`Layers`] of the grid that does the solving.
+    pub fn layers(&self) -> &WaveFunction<Layers> {
+        &self.wave_function
+    }
+    /// Returns the counters of the solve so far, see [`WaveFunction::stats`].
+    pub fn stats(&self) -> SolveStats {
+        self.wave_function.stats()
+    }
+
+    /// Collapses the cell with the lowest entropy to a random tile and propagates the result.
+    ///
+    /// Returns [`Contradiction`] if propagation ran into a cell without any possible tile.
+    pub fn collapse<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Result<(), Contradiction> {
+        self.wave_function.collapse(rng)
+    }
+    /// Like [`VoxelWaveFunction::collapse`], reporting every step to `observer`.
+    pub fn collapse_with<R: Rng + ?Sized, O: Observer + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        observer: &mut O,
+    ) -> Result<(), Contradiction> {
+        self.wave_function.collapse_with(rng, observer)
+    }
+    /// Takes back the last collapse, see [`WaveFunction::undo`].
+    pub fn undo(&mut self) -> Option<(VoxelCoordinates, usize)> {
+        let (cell, tile) = self.wave_function.undo()?;
+        let layers = self.wave_function.get_topology();
+        Some((layers.voxel(cell, self.wave_function.shape()), tile))
+    }
+
+    /// Collapses cells until the wave function is done or runs into a contradiction.
+    pub fn run<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Result<(), Contradiction> {
+        self.wave_function.run(rng)
+    }
+
+    /// Runs attempts until one of them succeeds or `limits` are hit, like
+    /// [`WaveFunction::solve`].
+    pub fn solve<R: Rng + ?Sized, O: Observer + ?Sized>(
+        self,
+        rng: &mut R,
+        limits: Limits,
+        observer: &mut O,
+    ) -> Result<Self, GaveUp<Layers>> {
+        let wave_function = self.wave_function.solve(rng, limits, observer)?;
+        Ok(Self {
+            wave_function,
+            ..self
+        })
+    }
+
+    /// Returns the tile of the cell at `coords`, or `None` while it isn't collapsed.
+    pub fn get_tile_id(&self, coords: VoxelCoordinates) -> Option<usize> {
+        let layers = self.wave_function.get_topology();
+        self.wave_function
+            .get_tile_id(layers.cell(coords, self.wave_function.shape()))
+    }
+
+    /// Returns the tile id of every cell, or `None` while the wave function isn't done.
+    pub fn get_result(&self) -> Option<Array3<usize>> {
+        if !self.done() {
+            return None;
+        }
+        let [shape_x, shape_y, shape_z] = self.shape;
+        Some(Array3::from_shape_fn(
+            (shape_x, shape_y, shape_z),
+            |coords| self.get_tile_id(coords).unwrap(),
+        ))
+    }
+}
+
+impl VoxelTileset {
+    /// The tiles as a [`Tileset`] over the six directions of [`Layers`], each with the
+    /// bottom layer of its voxels as image.
+    fn flattened(&self) -> Tileset {
+        let tiles = (0..self.len())
+            .map(|id| {
+                let tile = &self[id];
+                let neighbours = VoxelDirection::ALL
+                    .iter()
+                    .map(|&direction| tile.get_neighbours(direction).clone())
+                    .collect();
+                Tile::new(
+                    tile.get_voxels().index_axis(ndarray::Axis(2), 0).to_owned(),
+                    tile.get_weight(),
+                    neighbours,
+                )
+            })
+            .collect();
+        Tileset::from_tiles(tiles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAPE: [usize; 2] = [3, 8];
+    const LAYERS: Layers = Layers { depth: 2 };
+
+    #[test]
+    fn cells_map_to_voxels_and_back() {
+        for cell in ndarray::indices((SHAPE[0], SHAPE[1])) {
+            let voxel = LAYERS.voxel(cell, SHAPE);
+            assert!(voxel.1 < 4 && voxel.2 < 2);
+            assert_eq!(LAYERS.cell(voxel, SHAPE), cell);
+        }
+    }
+
+    #[test]
+    fn neighbours_step_through_the_layers() {
+        let cell = LAYERS.cell((1, 3, 0), SHAPE);
+        let neighbours: Vec<Option<VoxelCoordinates>> = (0..LAYERS.directions())
+            .map(|direction| {
+                LAYERS
+                    .neighbour(cell, direction, SHAPE)
+                    .map(|neighbour| LAYERS.voxel(neighbour, SHAPE))
+            })
+            .collect();
+        // the last row of a layer doesn't touch the first row of the next one
+        assert_eq!(
+            neighbours,
+            [
+                Some((0, 3, 0)),
+                Some((2, 3, 0)),
+                Some((1, 2, 0)),
+                None,
+                None,
+                Some((1, 3, 1)),
+            ]
+        );
+        for cell in ndarray::indices((SHAPE[0], SHAPE[1])) {
+            for direction in 0..LAYERS.directions() {
+                if let Some(neighbour) = LAYERS.neighbour(cell, direction, SHAPE) {
+                    let back = LAYERS.opposite(direction);
+                    assert_eq!(LAYERS.neighbour(neighbour, back, SHAPE), Some(cell));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::{fs, io::Write, path::Path};
+
+use image::{Rgb, RgbImage};
+use ndarray::{indices, s, Array3};
+
+use super::VoxelWaveFunction;
+use crate::wfc::tileset::{TilesetError, WfcColor, TILE_SIZE};
+
+// same gray the 2D render uses for cells that aren't collapsed yet
+const UNCOLLAPSED: [u8; 3] = [130, 130, 130];
+// limits of the MagicaVoxel format
+const MAX_VOX_SIZE: usize = 256;
+const MAX_VOX_COLORS: usize = 255;
+
+impl VoxelWaveFunction {
+    /// Returns the color of every voxel of the output, indexed `[x, y, z]`, `None` in
+    /// cells that aren't collapsed yet.
+    pub fn voxels(&self) -> Array3<Option<WfcColor>> {
+        let [shape_x, shape_y, shape_z] = self.shape;
+        let mut voxels = Array3::from_elem(
+            (
+                shape_x * TILE_SIZE,
+                shape_y * TILE_SIZE,
+                shape_z * TILE_SIZE,
+            ),
+            None,
+        );
+        for (x, y, z) in indices((shape_x, shape_y, shape_z)) {
+            if let Some(tile_id) = self.get_tile_id((x, y, z)) {
+                let (x, y, z) = (x * TILE_SIZE, y * TILE_SIZE, z * TILE_SIZE);
+                voxels
+                    .slice_mut(s![x..x + TILE_SIZE, y..y + TILE_SIZE, z..z + TILE_SIZE])
+                    .zip_mut_with(&self.tileset[tile_id].get_voxels(), |voxel, &color| {
+                        *voxel = Some(color)
+                    });
+            }
+        }
+        voxels
+    }
+
+    /// Writes the output as one png per voxel layer to `dir`, `slice_000.png` is the
+    /// bottom layer, the same layout [`VoxelTileset::from_slices`] reads. Cells that
+    /// aren't collapsed yet are gray.
+    ///
+    /// [`VoxelTileset::from_slices`]: super::tileset::VoxelTileset::from_slices
+    pub fn save_slices(&self, dir: &str) -> Result<(), TilesetError> {
+        fs::create_dir_all(dir)?;
+        let voxels = self.voxels();
+        let (width, height, depth) = voxels.dim();
+        for z in 0..depth {
+            let image = RgbImage::from_fn(width as u32, height as u32, |x, y| {
+                Rgb(voxels[[x as usize, y as usize, z]].map_or(UNCOLLAPSED, <[u8; 3]>::from))
+            });
+            image.save(Path::new(dir).join(format!("slice_{z:03}.png")))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the output as a MagicaVoxel `.vox` model to `path`.
+    ///
+    /// Voxels of the `empty` color and cells that aren't collapsed yet are left out.
+    /// The model may be at most 256 voxels along every axis and use at most 255 colors.
+    pub fn save_vox(&self, path: &str, empty: Option<WfcColor>) -> Result<(), TilesetError> {
+        let voxels = self.voxels();
+        let (width, height, depth) = voxels.dim();
+        if width > MAX_VOX_SIZE || height > MAX_VOX_SIZE || depth > MAX_VOX_SIZE {
+            return Err(TilesetError::Invalid(format!(
+                "a .vox model is at most {MAX_VOX_SIZE} voxels along every axis, got {width}x{height}x{depth}"
+            )));
+        }
+
+        let mut palette: Vec<WfcColor> = Vec::new();
+        let mut xyzi: Vec<u8> = Vec::new();
+        for ((x, y, z), &color) in voxels.indexed_iter() {
+            let color = match color {
+                Some(color) if Some(color) != empty => color,
+                _ => continue,
+            };
+            let index = match palette.iter().position(|&c| c == color) {
+                Some(index) => index,
+                None => {
+                    palette.push(color);
+                    palette.len() - 1
+                }
+            };
+            if palette.len() > MAX_VOX_COLORS {
+                return Err(TilesetError::Invalid(format!(
+                    "a .vox model has at most {MAX_VOX_COLORS} colors"
+                )));
+            }
+            // MagicaVoxel has z pointing up and y pointing away from the viewer, slices
+            // have y pointing down so the rows are flipped to keep the model unmirrored
+            xyzi.extend([x as u8, (height - 1 - y) as u8, z as u8, index as u8 + 1]);
+        }
+
+        let mut size_content = Vec::new();
+        for dim in [width, height, depth] {
+            size_content.extend((dim as u32).to_le_bytes());
+        }
+        let mut xyzi_content = ((xyzi.len() / 4) as u32).to_le_bytes().to_vec();
+        xyzi_content.extend(xyzi);
+        // palette entry `i` is color index `i + 1`, unused entries stay black
+        let mut rgba_content = vec![0; 4 * 256];
+        for (i, &color) in palette.iter().enumerate() {
+            let [r, g, b] = <[u8; 3]>::from(color);
+            rgba_content[4 * i..4 * i + 4].copy_from_slice(&[r, g, b, 255]);
+        }
+
+        let mut children = Vec::new();
+        write_chunk(&mut children, b"SIZE", &size_content, &[]);
+        write_chunk(&mut children, b"XYZI", &xyzi_content, &[]);
+        write_chunk(&mut children, b"RGBA", &rgba_content, &[]);
+
+        let mut file = b"VOX ".to_vec();
+        file.extend(150u32.to_le_bytes());
+        write_chunk(&mut file, b"MAIN", &[], &children);
+        fs::File::create(path)?.write_all(&file)?;
+        Ok(())
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8], children: &[u8]) {
+    out.extend(id);
+    out.extend((content.len() as u32).to_le_bytes());
+    out.extend((children.len() as u32).to_le_bytes());
+    out.extend(content);
+    out.extend(children);
+}
@@ -0,0 +1,237 @@
+use std::{
+    fmt,
+    ops::{Index, IndexMut},
+};
+
+use ndarray::{s, Array3, ArrayView3, Axis};
+
+use crate::wfc::tileset::{read_png, TilesetError, WfcColor, TILE_SIZE};
+
+/// Side of a voxel tile, `Left` and `Right` along `x`, `Up` and `Down` along `y` like
+/// in a slice image, `Below` and `Above` along `z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VoxelDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+    Below,
+    Above,
+}
+impl VoxelDirection {
+    pub const ALL: [VoxelDirection; 6] = [
+        VoxelDirection::Left,
+        VoxelDirection::Right,
+        VoxelDirection::Up,
+        VoxelDirection::Down,
+        VoxelDirection::Below,
+        VoxelDirection::Above,
+    ];
+
+    pub fn opposite(self) -> VoxelDirection {
+        match self {
+            VoxelDirection::Left => VoxelDirection::Right,
+            VoxelDirection::Right => VoxelDirection::Left,
+            VoxelDirection::Up => VoxelDirection::Down,
+            VoxelDirection::Down => VoxelDirection::Up,
+            VoxelDirection::Below => VoxelDirection::Above,
+            VoxelDirection::Above => VoxelDirection::Below,
+        }
+    }
+
+    /// Returns the `(dx, dy, dz)` step to the neighbour on this side.
+    pub fn offset(self) -> (isize, isize, isize) {
+        match self {
+            VoxelDirection::Left => (-1, 0, 0),
+            VoxelDirection::Right => (1, 0, 0),
+            VoxelDirection::Up => (0, -1, 0),
+            VoxelDirection::Down => (0, 1, 0),
+            VoxelDirection::Below => (0, 0, -1),
+            VoxelDirection::Above => (0, 0, 1),
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+impl fmt::Display for VoxelDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VoxelDirection::Left => "left",
+            VoxelDirection::Right => "right",
+            VoxelDirection::Up => "up",
+            VoxelDirection::Down => "down",
+            VoxelDirection::Below => "below",
+            VoxelDirection::Above => "above",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A `TILE_SIZE`×`TILE_SIZE`×`TILE_SIZE` block of the voxel sample together with the
+/// ids of the tiles that may appear next to it, see [`Tile`](crate::Tile) for the
+/// meaning of `None` and the weight.
+#[derive(Clone)]
+pub struct VoxelTile {
+    voxels: Array3<WfcColor>,
+    weight: f64,
+    // indexed by `VoxelDirection::index`
+    neighbours: [Vec<Option<usize>>; 6],
+}
+impl VoxelTile {
+    fn add_neighbour(&mut self, direction: VoxelDirection, id: Option<usize>) {
+        let neighbours = &mut self.neighbours[direction.index()];
+        if !neighbours.contains(&id) {
+            neighbours.push(id);
+        }
+    }
+
+    /// Returns `true` if the tile may be placed on the `direction` border of the output.
+    pub fn is_border(&self, direction: VoxelDirection) -> bool {
+        self.neighbours[direction.index()].contains(&None)
+    }
+
+    /// Returns the ids of the tiles that may appear on the `direction` side of this one.
+    pub fn get_neighbours(&self, direction: VoxelDirection) -> &Vec<Option<usize>> {
+        &self.neighbours[direction.index()]
+    }
+
+    /// Returns the relative frequency the tile is picked with.
+    pub fn get_weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// Returns the voxels of the tile, indexed `[x, y, z]`.
+    pub fn get_voxels(&self) -> ArrayView3<'_, WfcColor> {
+        self.voxels.view()
+    }
+}
+
+/// All voxel tiles learned from a sample, indexed by tile id.
+#[derive(Clone)]
+pub struct VoxelTileset {
+    tiles: Vec<VoxelTile>,
+}
+impl IndexMut<usize> for VoxelTileset {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.tiles[index]
+    }
+}
+impl Index<usize> for VoxelTileset {
+    type Output = VoxelTile;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.tiles[index]
+    }
+}
+impl VoxelTileset {
+    /// Returns the number of tiles.
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Learns a tileset from a voxel sample given as png slices, ordered from the
+    /// bottom layer up.
+    ///
+    /// All slices need the same size, width, height and the number of slices have to
+    /// be multiples of [`TILE_SIZE`].
+    pub fn from_slices(paths: &[&str]) -> Result<Self, TilesetError> {
+        if paths.is_empty() || paths.len() % TILE_SIZE != 0 {
+            return Err(TilesetError::Invalid(format!(
+                "number of slices must be a multiple of tilesize ({TILE_SIZE}), got {}",
+                paths.len()
+            )));
+        }
+        let slices = paths
+            .iter()
+            .map(|path| read_png(path))
+            .collect::<Result<Vec<_>, TilesetError>>()?;
+        if let Some(index) = slices
+            .iter()
+            .position(|slice| slice.dim() != slices[0].dim())
+        {
+            return Err(TilesetError::Invalid(format!(
+                "{}: all slices must have the same size as {}",
+                paths[index], paths[0]
+            )));
+        }
+        let views: Vec<_> = slices.iter().map(|slice| slice.view()).collect();
+        let sample: Array3<WfcColor> =
+            ndarray::stack(Axis(2), &views).expect("slices have the same size");
+
+        let mut tileset = VoxelTileset { tiles: Vec::new() };
+        tileset.add_sample(&sample)?;
+        Ok(tileset)
+    }
+
+    fn get_id(&self, block: ArrayView3<WfcColor>) -> Option<usize> {
+        self.tiles.iter().position(|tile| tile.voxels == block)
+    }
+
+    fn add_sample(&mut self, sample: &Array3<WfcColor>) -> Result<(), TilesetError> {
+        let (width, height, depth) = sample.dim();
+        if width == 0 || width % TILE_SIZE != 0 {
+            return Err(TilesetError::Invalid(format!(
+                "width of the slices must be multiple of tilesize ({TILE_SIZE})"
+            )));
+        }
+        if height == 0 || height % TILE_SIZE != 0 {
+            return Err(TilesetError::Invalid(format!(
+                "height of the slices must be multiple of tilesize ({TILE_SIZE})"
+            )));
+        }
+        let blocks = (width / TILE_SIZE, height / TILE_SIZE, depth / TILE_SIZE);
+        let get_block = |(bx, by, bz): (usize, usize, usize)| {
+            let (x, y, z) = (bx * TILE_SIZE, by * TILE_SIZE, bz * TILE_SIZE);
+            sample.slice(s![x..x + TILE_SIZE, y..y + TILE_SIZE, z..z + TILE_SIZE])
+        };
+
+        // creating all tiles
+        let mut ids: Array3<usize> = Array3::zeros(blocks);
+        for ((bx, by, bz), id) in ids.indexed_iter_mut() {
+            let block = get_block((bx, by, bz));
+            *id = match self.get_id(block) {
+                Some(id) => {
+                    self.tiles[id].weight += 1.0;
+                    id
+                }
+                None => {
+                    self.tiles.push(VoxelTile {
+                        voxels: block.to_owned(),
+                        weight: 1.0,
+                        neighbours: Default::default(),
+                    });
+                    self.tiles.len() - 1
+                }
+            };
+        }
+
+        // computing neighbours
+        for ((bx, by, bz), &id) in ids.indexed_iter() {
+            for direction in VoxelDirection::ALL {
+                let (dx, dy, dz) = direction.offset();
+                let neighbour = (bx as isize + dx, by as isize + dy, bz as isize + dz);
+                let inside = neighbour.0 >= 0
+                    && neighbour.1 >= 0
+                    && neighbour.2 >= 0
+                    && (neighbour.0 as usize) < blocks.0
+                    && (neighbour.1 as usize) < blocks.1
+                    && (neighbour.2 as usize) < blocks.2;
+                let neighbour_id = inside.then(|| {
+                    ids[[
+                        neighbour.0 as usize,
+                        neighbour.1 as usize,
+                        neighbour.2 as usize,
+                    ]]
+                });
+                self[id].add_neighbour(direction, neighbour_id);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+use std::{collections::HashMap, sync::Arc};
+
+use image::{Rgb, RgbImage};
+use ndarray::Array2;
+use rand::SeedableRng;
+
+use super::{
+    limits::{GaveUp, GiveUpReason, Limits},
+    tileset::{draw_tile, Direction, Tileset, TilesetError, TILE_SIZE},
+    topology::{Periodic, Square, Topology},
+    SolverRng, WaveFunction,
+};
+
+/// Attempts a chunk gets before [`World::chunk`] gives up, unless set otherwise.
+pub const DEFAULT_CHUNK_ATTEMPTS: usize = 100;
+
+// same gray the square render uses for cells that aren't collapsed yet
+const UNGENERATED: Rgb<u8> = Rgb([130, 130, 130]);
+
+/// Position of a chunk in the world, chunk `(0, 0)` covers the cells from `(0, 0)` up
+/// to the chunk size and `(-1, 0)` is the chunk left of it.
+pub type ChunkCoordinates = (i64, i64);
+
+/// An unbounded output, solved in chunks of a fixed size when they are first asked for.
+///
+/// The edges of chunks that are already generated are fixed constraints for their
+/// neighbours, so the world has no seams. A chunk only depends on the world seed, its
+/// coordinates and the chunks around it, so generating the same chunks in the same
+/// order gives the same world.
+pub struct World {
+    tileset: Arc<Tileset>,
+    chunk_size: (usize, usize),
+    seed: u64,
+    limits: Limits,
+    chunks: HashMap<ChunkCoordinates, Array2<usize>>,
+}
+
+impl World {
+    /// Fails if `tileset` wasn't learned for the four sides.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chunk size is zero in either dimension.
+    pub fn new(
+        tileset: impl Into<Arc<Tileset>>,
+        chunk_size: (usize, usize),
+        seed: u64,
+    ) -> Result<Self, TilesetError> {
+        let tileset = tileset.into();
+        assert!(
+            chunk_size.0 > 0 && chunk_size.1 > 0,
+            "chunks must contain at least one cell"
+        );
+        tileset.require_sides("worlds")?;
+        Ok(Self {
+            tileset,
+            chunk_size,
+            seed,
+            limits: Limits {
+                max_attempts: Some(DEFAULT_CHUNK_ATTEMPTS),
+                ..Limits::default()
+            },
+            chunks: HashMap::new(),
+        })
+    }
+
+    /// Sets when to give up on a chunk, applies to every chunk generated from now on.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+    pub fn chunk_size(&self) -> (usize, usize) {
+        self.chunk_size
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn get_tileset(&self) -> &Tileset {
+        &self.tileset
+    }
+
+    /// Returns the tile ids of the chunk at `coords` if it's generated, indexed `[x, y]`.
+    pub fn get_chunk(&self, coords: ChunkCoordinates) -> Option<&Array2<usize>> {
+        self.chunks.get(&coords)
+    }
+    /// Coordinates of every chunk that is generated or inserted.
+    pub fn chunks(&self) -> impl Iterator<Item = ChunkCoordinates> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    /// Returns the chunk at `coords`, generating it first if needed.
+    pub fn chunk(&mut self, coords: ChunkCoordinates) -> Result<&Array2<usize>, GaveUp> {
+        if !self.chunks.contains_key(&coords) {
+            let tiles = self.generate(coords)?;
+            self.chunks.insert(coords, tiles);
+        }
+        Ok(&self.chunks[&coords])
+    }
+
+    /// Returns the tile id at the world cell `(x, y)`, generating its chunk if needed.
+    pub fn tile_at(&mut self, (x, y): (i64, i64)) -> Result<usize, GaveUp> {
+        let (width, height) = (self.chunk_size.0 as i64, self.chunk_size.1 as i64);
+        let tiles = self.chunk((x.div_euclid(width), y.div_euclid(height)))?;
+        Ok(tiles[[x.rem_euclid(width) as usize, y.rem_euclid(height) as usize]])
+    }
+
+    /// Forgets the chunk at `coords` and returns its tiles, for example to store them
+    /// once the player is far away. Chunks generated next to it afterwards are no
+    /// longer constrained by it.
+    pub fn unload(&mut self, coords: ChunkCoordinates) -> Option<Array2<usize>> {
+        self.chunks.remove(&coords)
+    }
+
+    /// Puts back a chunk returned by [`World::unload`], or one made by hand.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tiles` doesn't have the chunk size or contains ids that aren't in
+    /// the tileset.
+    pub fn insert_chunk(&mut self, coords: ChunkCoordinates, tiles: Array2<usize>) {
+        assert_eq!(
+            tiles.dim(),
+            self.chunk_size,
+            "chunk doesn't have the size of the world's chunks"
+        );
+        assert!(
+            tiles.iter().all(|&id| id < self.tileset.len()),
+            "chunk contains tiles that aren't in the tileset"
+        );
+        self.chunks.insert(coords, tiles);
+    }
+
+    /// Renders the chunks from `from` to `to`, both included, at one pixel per tile
+    /// pixel. Chunks that aren't generated are gray.
+    pub fn render(&self, from: ChunkCoordinates, to: ChunkCoordinates) -> RgbImage {
+        let (width, height) = self.chunk_size;
+        let chunks_x = (to.0 - from.0 + 1).max(0) as usize;
+        let chunks_y = (to.1 - from.1 + 1).max(0) as usize;
+        let mut image = RgbImage::from_pixel(
+            (chunks_x * width * TILE_SIZE) as u32,
+            (chunks_y * height * TILE_SIZE) as u32,
+            UNGENERATED,
+        );
+        for chunk_x in 0..chunks_x {
+            for chunk_y in 0..chunks_y {
+                let coords = (from.0 + chunk_x as i64, from.1 + chunk_y as i64);
+                let Some(tiles) = self.chunks.get(&coords) else {
+                    continue;
+                };
+                for ((x, y), &tile_id) in tiles.indexed_iter() {
+                    let corner = (
+                        ((chunk_x * width + x) * TILE_SIZE) as u32,
+                        ((chunk_y * height + y) * TILE_SIZE) as u32,
+                    );
+                    draw_tile(&mut image, &self.tileset[tile_id], corner, 1);
+                }
+            }
+        }
+        image
+    }
+
+    fn generate(&self, coords: ChunkCoordinates) -> Result<Array2<usize>, GaveUp> {
+        let (width, height) = self.chunk_size;
+        let mut wave_function =
+            WaveFunction::without_borders(self.chunk_size, self.tileset.clone());
+
+        // the world continues past every edge: the cells there need a tile of the
+        // neighbouring chunk if it exists and at least some tile that can follow otherwise
+        let shape = [width, height];
+        for direction in Direction::ALL {
+            let (dx, dy) = match direction {
+                Direction::Left => (-1, 0),
+                Direction::Right => (1, 0),
+                Direction::Up => (0, -1),
+                Direction::Down => (0, 1),
+            };
+            // cells without a neighbour in this chunk, paired with the cell next to them
+            // in the neighbouring chunk, which is where the chunk would wrap around to
+            let edge = (0..width)
+                .flat_map(|x| (0..height).map(move |y| (x, y)))
+                .filter(|&cell| Square.neighbour(cell, direction.index(), shape).is_none())
+                .map(|cell| {
+                    let wrapped = Periodic.neighbour(cell, direction.index(), shape);
+                    (
+                        cell,
+                        wrapped.expect("periodic cells always have neighbours"),
+                    )
+                });
+            let neighbour = self.chunks.get(&(coords.0 + dx, coords.1 + dy));
+            for (cell, neighbour_cell) in edge {
+                let result = match neighbour {
+                    Some(tiles) => {
+                        let facing = self.tileset[tiles[neighbour_cell]]
+                            .get_neighbours(direction.opposite());
+                        wave_function.restrict(cell, |tile| facing.contains(&Some(tile)))
+                    }
+                    None => wave_function.restrict(cell, |tile| {
+                        self.tileset[tile]
+                            .get_neighbours(direction)
+                            .iter()
+                            .any(Option::is_some)
+                    }),
+                };
+                if result.is_err() {
+                    return Err(GaveUp {
+                        reason: GiveUpReason::Unsatisfiable,
+                        attempts: 0,
+                        best: Box::new(WaveFunction::without_borders(
+                            self.chunk_size,
+                            self.tileset.clone(),
+                        )),
+                    });
+                }
+            }
+        }
+
+        let mut rng = chunk_rng(self.seed, coords);
+        let solved = wave_function.solve(&mut rng, self.limits.clone(), &mut ())?;
+        Ok(solved
+            .get_result()
+            .expect("a solved wave function has every cell collapsed"))
+    }
+}
+
+// every chunk gets its own rng stream, only depending on the world seed and where it is
+fn chunk_rng(seed: u64, (x, y): ChunkCoordinates) -> SolverRng {
+    let mut bytes = [0; 32];
+    bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    bytes[8..16].copy_from_slice(&x.to_le_bytes());
+    bytes[16..24].copy_from_slice(&y.to_le_bytes());
+    SolverRng::from_seed(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wfc::{hex::Hex, tileset::fixtures};
+
+    const CHUNK_SIZE: (usize, usize) = (4, 5);
+    // generated in this order, so chunks meet neighbours on every side
+    const CHUNKS: [ChunkCoordinates; 6] = [(0, 0), (1, 0), (0, 1), (-1, 0), (1, 1), (0, -1)];
+
+    fn generate(seed: u64) -> World {
+        let mut world = World::new(fixtures::tileset(), CHUNK_SIZE, seed).unwrap();
+        for coords in CHUNKS {
+            world.chunk(coords).unwrap();
+        }
+        world
+    }
+
+    #[test]
+    fn chunks_have_no_seams() {
+        let world = generate(0);
+        let (width, height) = (CHUNK_SIZE.0 as i64, CHUNK_SIZE.1 as i64);
+        let tile_at = |(x, y): (i64, i64)| {
+            let tiles = world.get_chunk((x.div_euclid(width), y.div_euclid(height)))?;
+            Some(tiles[[x.rem_euclid(width) as usize, y.rem_euclid(height) as usize]])
+        };
+        let mut seams = 0;
+        for (chunk_x, chunk_y) in CHUNKS {
+            for x in chunk_x * width..(chunk_x + 1) * width {
+                for y in chunk_y * height..(chunk_y + 1) * height {
+                    let tile = tile_at((x, y)).unwrap();
+                    for (direction, neighbour) in [
+                        (Direction::Right, (x + 1, y)),
+                        (Direction::Down, (x, y + 1)),
+                    ] {
+                        let Some(neighbour_tile) = tile_at(neighbour) else {
+                            continue;
+                        };
+                        let crosses_chunks = neighbour.0.div_euclid(width) != chunk_x
+                            || neighbour.1.div_euclid(height) != chunk_y;
+                        seams += usize::from(crosses_chunks);
+                        assert!(
+                            world.get_tileset()[tile]
+                                .get_neighbours(direction)
+                                .contains(&Some(neighbour_tile)),
+                            "tile {neighbour_tile} at {neighbour:?} can't follow {tile} at {:?}",
+                            (x, y)
+                        );
+                    }
+                }
+            }
+        }
+        // (0, 0) with its four neighbours, (1, 1) with (1, 0) and (0, 1)
+        assert_eq!(seams, 3 * CHUNK_SIZE.1 + 3 * CHUNK_SIZE.0);
+    }
+
+    #[test]
+    fn same_seed_and_order_give_the_same_world() {
+        let (first, second) = (generate(7), generate(7));
+        for coords in CHUNKS {
+            assert_eq!(first.get_chunk(coords), second.get_chunk(coords));
+        }
+        let other = generate(8);
+        assert!(CHUNKS
+            .iter()
+            .any(|&coords| other.get_chunk(coords) != first.get_chunk(coords)));
+    }
+
+    #[test]
+    fn tilesets_need_the_four_sides() {
+        let hex = fixtures::learn(&fixtures::sample(), &Hex::offset());
+        assert!(matches!(
+            World::new(hex, CHUNK_SIZE, 0),
+            Err(TilesetError::Invalid(_))
+        ));
+    }
+}